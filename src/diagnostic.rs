@@ -0,0 +1,96 @@
+use crate::enums::LexErrorItem;
+use crate::enums::LexErrorKind;
+use crate::enums::ParseError;
+use crate::enums::RuntimeError;
+use crate::enums::Span;
+
+///
+/// ソースコード上の位置を指し示せるエラー
+///
+pub trait Diagnostic {
+    fn span(&self) -> Span;
+    fn message(&self) -> String;
+}
+
+impl Diagnostic for LexErrorItem {
+    fn span(&self) -> Span { self.span }
+
+    fn message(&self) -> String {
+        match &self.kind {
+            LexErrorKind::UnexpectedChar(c) => format!("予期しない文字 {:?} を検知した", c),
+            LexErrorKind::InvalidNumber(s) => format!("数値 {} を表現できない", s),
+            LexErrorKind::UnterminatedString => "文字列が閉じられていない".to_string(),
+            LexErrorKind::InvalidEscape(s) => format!("エスケープシーケンス {} は解釈できない", s),
+        }
+    }
+}
+
+impl Diagnostic for ParseError {
+    fn span(&self) -> Span { self.span }
+
+    fn message(&self) -> String {
+        let found = match &self.found {
+            Some(token) => token.to_string(),
+            None => "入力の終わり".to_string(),
+        };
+        format!("{} を想定していたが想定外の {} がきた", self.expected.join(" または "), found)
+    }
+}
+
+impl Diagnostic for RuntimeError {
+    fn span(&self) -> Span { self.span }
+
+    fn message(&self) -> String { self.message.clone() }
+}
+
+///
+/// エラーを該当行と '^' による下線つきで整形する
+///
+/// ```text
+/// エラー: 加算処理が未定義
+///  --> 1行5列目
+///   |
+/// 1 | x = 1 + "a"
+///   |     ^^^^^^^
+/// ```
+///
+pub fn render(source: &str, error: &dyn Diagnostic) -> String {
+    let span = error.span();
+    let start = usize::min(span.start, source.len());
+
+    // 該当する行を切り出す
+    let line_start = source[..start].rfind('\n').map_or(0, |i| i + 1);
+    let line_end = source[start..].find('\n').map_or(source.len(), |i| start + i);
+    let line = source[line_start..line_end].trim_end_matches('\r');
+    let end = usize::min(usize::max(span.end, start), line_start + line.len());
+
+    // 下線の位置と長さは表示幅で数える
+    let indent = width(&source[line_start..start]);
+    let length = usize::max(width(&source[start..end]), 1);
+
+    let number = span.line.to_string();
+    let margin = " ".repeat(number.len());
+    format!(
+        "エラー: {}\n{} --> {}行{}列目\n{} |\n{} | {}\n{} | {}{}\n",
+        error.message(),
+        margin,
+        span.line,
+        span.col,
+        margin,
+        number,
+        line.replace('\t', " "),
+        margin,
+        " ".repeat(indent),
+        "^".repeat(length)
+    )
+}
+
+/// 端末上での表示幅を返却する（全角文字は 2、タブは空白に置き換えて 1 として数える）
+fn width(s: &str) -> usize {
+    s.chars()
+        .map(|c| match c as u32 {
+            0x1100..=0x115F | 0x2E80..=0xA4CF | 0xAC00..=0xD7A3 | 0xF900..=0xFAFF | 0xFE30..=0xFE4F | 0xFF00..=0xFF60 | 0xFFE0..=0xFFE6 | 0x20000..=0x3FFFD => 2,
+            _ => 1,
+        })
+        .sum()
+}
@@ -1,22 +1,68 @@
+use std::cell::RefCell;
+use std::collections::BTreeMap;
 use std::collections::HashMap;
+use std::fmt;
+use std::rc::Rc;
+
+use num_bigint::BigInt;
+use num_complex::Complex64;
+use num_integer::Integer;
+use num_rational::BigRational;
+use num_traits::ToPrimitive;
+use num_traits::Zero;
+use rust_decimal::Decimal;
+use rust_decimal::RoundingStrategy;
+
+use crate::diagnostic::Diagnostic;
+use crate::env::Env;
+use crate::evaluator::Context;
+use crate::unit::Quantity;
+use crate::unit::Unit;
 
-pub type Env = HashMap<String, Value>;
 pub type FunctionTable = HashMap<String, Declaration>;
 
+/// 構造体の型名とフィールド名の一覧
+pub type TypeTable = HashMap<String, Vec<String>>;
+
+#[allow(clippy::upper_case_acronyms)]
 #[derive(Debug, PartialEq, Clone)]
 pub enum Token {
     PLUS,
     MINUS,
     STAR,
     SLASH,
+    STARSTAR,
+    SLASHSLASH,
+    PERCENT,
+    AMP,
+    CARET,
+    TILDE,
+    SHL,
+    SHR,
     LPAR,
     RPAR,
     LBRACE,
     RBRACE,
+    LBRACKET,
+    RBRACKET,
     EQ,
+    EQEQ,
+    NOTEQ,
     GT,
     LT,
+    GE,
+    LE,
+    ANDAND,
+    OROR,
+    NOT,
+    TRUE,
+    FALSE,
     NUMBER(i32),
+    BIGNUMBER(BigInt),
+    FLOAT(f64),
+    DECIMAL(Decimal),
+    IMAGINARY(f64),
+    UNIT(Unit),
     IF,
     ELSE,
     IDENT(String),
@@ -24,7 +70,162 @@ pub enum Token {
     SEMICOLON,
     COMMA,
     FN,
+    STRUCT,
     RETURN,
+    GLOBAL,
+    NONLOCAL,
+    PIPE,
+    WHILE,
+    FOR,
+    IN,
+    BREAK,
+    CONTINUE,
+    DOTDOT,
+    DOT,
+    COLON,
+    PRAGMA,
+}
+impl fmt::Display for Token {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Token::PLUS => write!(f, "'+'"),
+            Token::MINUS => write!(f, "'-'"),
+            Token::STAR => write!(f, "'*'"),
+            Token::SLASH => write!(f, "'/'"),
+            Token::STARSTAR => write!(f, "'**'"),
+            Token::SLASHSLASH => write!(f, "'//'"),
+            Token::PERCENT => write!(f, "'%'"),
+            Token::AMP => write!(f, "'&'"),
+            Token::CARET => write!(f, "'^'"),
+            Token::TILDE => write!(f, "'~'"),
+            Token::SHL => write!(f, "'<<'"),
+            Token::SHR => write!(f, "'>>'"),
+            Token::LPAR => write!(f, "'('"),
+            Token::RPAR => write!(f, "')'"),
+            Token::LBRACE => write!(f, "'{{'"),
+            Token::RBRACE => write!(f, "'}}'"),
+            Token::LBRACKET => write!(f, "'['"),
+            Token::RBRACKET => write!(f, "']'"),
+            Token::EQ => write!(f, "'='"),
+            Token::EQEQ => write!(f, "'=='"),
+            Token::NOTEQ => write!(f, "'!='"),
+            Token::GT => write!(f, "'>'"),
+            Token::LT => write!(f, "'<'"),
+            Token::GE => write!(f, "'>='"),
+            Token::LE => write!(f, "'<='"),
+            Token::ANDAND => write!(f, "'&&'"),
+            Token::OROR => write!(f, "'||'"),
+            Token::NOT => write!(f, "'!'"),
+            Token::TRUE => write!(f, "'true'"),
+            Token::FALSE => write!(f, "'false'"),
+            Token::NUMBER(n) => write!(f, "数値 {}", n),
+            Token::BIGNUMBER(n) => write!(f, "数値 {}", n),
+            Token::FLOAT(n) => write!(f, "数値 {}", n),
+            Token::DECIMAL(n) => write!(f, "数値 {}d", n),
+            Token::IMAGINARY(n) => write!(f, "数値 {}i", n),
+            Token::UNIT(u) => write!(f, "単位 {}", u),
+            Token::IF => write!(f, "'if'"),
+            Token::ELSE => write!(f, "'else'"),
+            Token::IDENT(s) => write!(f, "識別子 {}", s),
+            Token::STR(s) => write!(f, "文字列 {:?}", s),
+            Token::SEMICOLON => write!(f, "';'"),
+            Token::COMMA => write!(f, "','"),
+            Token::FN => write!(f, "'fn'"),
+            Token::STRUCT => write!(f, "'struct'"),
+            Token::RETURN => write!(f, "'return'"),
+            Token::GLOBAL => write!(f, "'global'"),
+            Token::NONLOCAL => write!(f, "'nonlocal'"),
+            Token::PIPE => write!(f, "'|'"),
+            Token::WHILE => write!(f, "'while'"),
+            Token::FOR => write!(f, "'for'"),
+            Token::IN => write!(f, "'in'"),
+            Token::BREAK => write!(f, "'break'"),
+            Token::CONTINUE => write!(f, "'continue'"),
+            Token::DOTDOT => write!(f, "'..'"),
+            Token::DOT => write!(f, "'.'"),
+            Token::COLON => write!(f, "':'"),
+            Token::PRAGMA => write!(f, "'pragma'"),
+        }
+    }
+}
+
+///
+/// ソースコード上の位置
+///
+/// * start, end - 先頭からのバイト位置（end は含まない）
+/// * line, col - start の行番号と列番号（いずれも 1 始まり、列は文字単位）
+///
+#[derive(Debug, PartialEq, Clone, Copy, Default)]
+pub struct Span {
+    pub start: usize,
+    pub end: usize,
+    pub line: usize,
+    pub col: usize,
+}
+impl Span {
+    /// self の先頭から other の末尾までの範囲を返却する
+    pub fn to(self, other: Span) -> Span {
+        Span {
+            end: usize::max(self.end, other.end),
+            ..self
+        }
+    }
+}
+
+///
+/// 位置情報つきのトークン
+///
+#[derive(Debug, PartialEq, Clone)]
+pub struct SpannedToken {
+    pub token: Token,
+    pub span: Span,
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub enum LexErrorKind {
+    UnexpectedChar(char),
+    InvalidNumber(String),
+    UnterminatedString,
+    InvalidEscape(String),
+}
+
+///
+/// 字句解析で検出したエラー 1 件
+///
+#[derive(Debug, PartialEq, Clone)]
+pub struct LexErrorItem {
+    pub kind: LexErrorKind,
+    pub span: Span,
+}
+impl fmt::Display for LexErrorItem {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{}行{}列目（{}バイト目）: {}",
+            self.span.line,
+            self.span.col,
+            self.span.start,
+            self.message()
+        )
+    }
+}
+
+///
+/// 字句解析のエラー
+///
+/// 1 回のスキャンで検出したすべてのエラーを保持する
+///
+#[derive(Debug, PartialEq, Clone)]
+pub struct LexError {
+    pub items: Vec<LexErrorItem>,
+}
+impl fmt::Display for LexError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for item in &self.items {
+            writeln!(f, "{}", item)?;
+        }
+        Ok(())
+    }
 }
 
 #[derive(Debug, PartialEq, Clone)]
@@ -33,109 +234,572 @@ pub enum BinOp {
     Sub,
     Mul,
     Div,
+    FloorDiv,
+    Rem,
+    Pow,
+    BitAnd,
+    BitOr,
+    BitXor,
+    Shl,
+    Shr,
 }
 
 #[derive(Debug, PartialEq, Clone)]
 pub enum ComparisonOp {
     Lt,
     Gt,
+    Le,
+    Ge,
     Eq,
+    Ne,
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub enum LogicalOp {
+    And,
+    Or,
 }
 
 #[derive(Debug, PartialEq, Clone)]
-pub enum Expr {
+pub struct Expr {
+    pub kind: ExprKind,
+    pub span: Span,
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub enum ExprKind {
     Binary {
         op: BinOp,
         lhs: Box<Expr>,
         rhs: Box<Expr>,
     },
+    // `a < b <= c` は lhs が a、rest が [(Lt, b), (Le, c)] になる
     Comparison {
-        op: ComparisonOp,
+        lhs: Box<Expr>,
+        rest: Vec<(ComparisonOp, Expr)>,
+    },
+    // 右辺は左辺の値によっては評価しない
+    Logical {
+        op: LogicalOp,
         lhs: Box<Expr>,
         rhs: Box<Expr>,
     },
+    Not(Box<Expr>),
+    Neg(Box<Expr>),
+    BitNot(Box<Expr>),
+    Bool(bool),
     Number(i32),
+    BigNumber(BigInt),
+    Float(f64),
+    Decimal(Decimal),
+    Imaginary(f64),
+    Quantity {
+        value: Box<Expr>,
+        unit: Unit,
+    },
     Var(String),
     Str(String),
     FunctionCall {
-        id: String,
+        callee: Box<Expr>,
         args: Vec<Expr>,
     },
+    List(Vec<Expr>),
+    Map(Vec<(String, Expr)>),
+    Struct {
+        name: String,
+        fields: Vec<(String, Expr)>,
+    },
+    Index {
+        target: Box<Expr>,
+        index: Box<Expr>,
+    },
+    Slice {
+        target: Box<Expr>,
+        start: Option<Box<Expr>>,
+        end: Option<Box<Expr>>,
+    },
+    Lambda {
+        arg: Vec<String>,
+        body: Rc<Statement>,
+    },
+    If {
+        condition: Box<Expr>,
+        then: Box<Expr>,
+        els: Option<Box<Expr>>,
+    },
 }
 
+///
+/// 構文解析で検出したエラー 1 件
+///
+/// * expected - 想定していたトークンの説明
+/// * found - 実際に出現したトークン（None は入力の終わり）
+///
 #[derive(Debug, PartialEq, Clone)]
-pub enum Statement {
+pub struct ParseError {
+    pub expected: Vec<String>,
+    pub found: Option<Token>,
+    pub span: Span,
+}
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result { write!(f, "{}行{}列目: {}", self.span.line, self.span.col, self.message()) }
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct Statement {
+    pub kind: StatementKind,
+    pub span: Span,
+}
+
+#[allow(clippy::enum_variant_names)]
+#[derive(Debug, PartialEq, Clone)]
+pub enum StatementKind {
     CompoundStatement {
         st1: Box<Statement>,
         st2: Box<Statement>,
     },
+    // path は `m.key[0] = e` のように変数の中の要素へ代入する場合のキーの並び
     Assign {
         id: String,
+        path: Vec<Expr>,
         e: Box<Expr>,
     },
     If {
         condition: Box<Expr>,
         then: Box<Statement>,
-        els: Box<Statement>,
+        els: Option<Box<Statement>>,
+    },
+    While {
+        condition: Box<Expr>,
+        body: Box<Statement>,
+    },
+    For {
+        id: String,
+        start: Box<Expr>,
+        end: Box<Expr>,
+        body: Box<Statement>,
     },
+    Break,
+    Continue,
     Return {
         expr: Box<Expr>,
     },
     FunctionDefine {
         id: String,
         arg: Vec<String>,
-        st: Box<Statement>,
+        st: Rc<Statement>,
     },
     FunctionCall {
         expr: Expr,
     },
+    StructDefine {
+        id: String,
+        fields: Vec<String>,
+    },
+    Global {
+        ids: Vec<String>,
+    },
+    Nonlocal {
+        ids: Vec<String>,
+    },
+    Pragma {
+        name: String,
+        value: Option<Box<Expr>>,
+    },
     Null,
 }
 
+///
+/// 文を実行した後の制御の流れ
+///
+/// * Normal - 次の文へ進む
+/// * Break - ループを抜ける
+/// * Continue - ループの次の繰り返しへ進む
+/// * Return - 関数から値を返して抜ける
+///
+#[derive(Debug, PartialEq, Clone)]
+pub enum Flow {
+    Normal,
+    Break,
+    Continue,
+    Return(Value),
+}
+
+///
+/// 実行時エラー
+///
+#[derive(Debug, PartialEq, Clone)]
+pub struct RuntimeError {
+    pub message: String,
+    pub span: Span,
+}
+impl fmt::Display for RuntimeError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result { write!(f, "{}行{}列目: {}", self.span.line, self.span.col, self.message) }
+}
+
+///
+/// 実行時の動作を切り替える設定
+///
+/// `pragma` 文もしくはコマンドライン引数で変更する
+///
+/// * rational - 整数どうしの除算の結果を有理数にする
+/// * scale - 小数の演算結果の小数点以下の桁数
+/// * rounding - 小数の演算結果を scale 桁に丸める方法
+/// * strict - 論理演算に真偽値以外の値を渡すとエラーにする
+/// * wrap - 整数の演算結果を固定幅の整数に折り返す（None なら折り返さない）
+///
+#[derive(Debug, PartialEq, Clone)]
+pub struct Settings {
+    pub rational: bool,
+    pub scale: u32,
+    pub rounding: Rounding,
+    pub strict: bool,
+    pub wrap: Option<Width>,
+}
+impl Default for Settings {
+    fn default() -> Self {
+        Settings {
+            rational: false,
+            scale: 2,
+            rounding: Rounding::default(),
+            strict: false,
+            wrap: None,
+        }
+    }
+}
+impl Settings {
+    ///
+    /// name の設定を value に変更する
+    ///
+    /// 有効・無効を切り替える設定は value を真偽値として解釈する
+    ///
+    pub fn set(&mut self, name: &str, value: Value) -> Result<(), String> {
+        match (name, value) {
+            ("rational", value) => self.rational = value.as_bool(),
+            ("scale", Value::Int(i)) if (0..=Decimal::MAX_SCALE as i32).contains(&i) => self.scale = i as u32,
+            ("scale", value) => return Err(format!("設定 scale には 0 以上 {} 以下の整数を指定するが {} が指定された", Decimal::MAX_SCALE, value)),
+            ("rounding", Value::String(s)) => match Rounding::parse(&s) {
+                Some(rounding) => self.rounding = rounding,
+                None => return Err(format!("丸め方 {:?} は存在しない", s)),
+            },
+            ("rounding", value) => return Err(format!("設定 rounding には丸め方の名前を指定するが {} が指定された", value)),
+            ("strict", value) => self.strict = value.as_bool(),
+            ("wrap", Value::String(s)) if s == "none" => self.wrap = None,
+            ("wrap", Value::String(s)) => match Width::parse(&s) {
+                Some(width) => self.wrap = Some(width),
+                None => return Err(format!("整数の幅 {:?} は存在しない", s)),
+            },
+            ("wrap", value) => return Err(format!("設定 wrap には整数の幅の名前を指定するが {} が指定された", value)),
+            (name, _) => return Err(format!("設定 {:?} は存在しない", name)),
+        }
+        Ok(())
+    }
+}
+
+///
+/// 小数の丸め方
+///
+/// * HalfEven - 最も近い値へ丸め、ちょうど中間の場合は偶数の側へ丸める（"half_even"）
+/// * HalfUp - 最も近い値へ丸め、ちょうど中間の場合は 0 から遠い側へ丸める（"half_up"）
+/// * Truncate - 0 方向へ切り捨てる（"truncate"）
+///
+#[derive(Debug, PartialEq, Clone, Copy, Default)]
+pub enum Rounding {
+    #[default]
+    HalfEven,
+    HalfUp,
+    Truncate,
+}
+impl Rounding {
+    /// 名前から丸め方を返却する
+    pub fn parse(name: &str) -> Option<Rounding> {
+        match name {
+            "half_even" => Some(Rounding::HalfEven),
+            "half_up" => Some(Rounding::HalfUp),
+            "truncate" => Some(Rounding::Truncate),
+            _ => None,
+        }
+    }
+
+    pub fn strategy(self) -> RoundingStrategy {
+        match self {
+            Rounding::HalfEven => RoundingStrategy::MidpointNearestEven,
+            Rounding::HalfUp => RoundingStrategy::MidpointAwayFromZero,
+            Rounding::Truncate => RoundingStrategy::ToZero,
+        }
+    }
+}
+
+///
+/// 固定幅の整数
+///
+/// * U8, U16, U32, U64 - 符号なし整数（"u8", "u16", "u32", "u64"）
+/// * I64 - 2 の補数で表す符号つき整数（"i64"）
+///
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum Width {
+    U8,
+    U16,
+    U32,
+    U64,
+    I64,
+}
+impl Width {
+    /// 名前から整数の幅を返却する
+    pub fn parse(name: &str) -> Option<Width> {
+        match name {
+            "u8" => Some(Width::U8),
+            "u16" => Some(Width::U16),
+            "u32" => Some(Width::U32),
+            "u64" => Some(Width::U64),
+            "i64" => Some(Width::I64),
+            _ => None,
+        }
+    }
+
+    /// ビット数
+    pub fn bits(self) -> u32 {
+        match self {
+            Width::U8 => 8,
+            Width::U16 => 16,
+            Width::U32 => 32,
+            Width::U64 | Width::I64 => 64,
+        }
+    }
+
+    /// n を幅に収まるよう、上位のビットを捨てて折り返す
+    pub fn wrap(self, n: &BigInt) -> BigInt {
+        let modulus = BigInt::from(1) << self.bits();
+        let n = n.mod_floor(&modulus);
+        if self == Width::I64 && n >= &modulus >> 1 {
+            n - modulus
+        } else {
+            n
+        }
+    }
+}
+
 pub trait AsBool {
     fn as_bool(&self) -> bool;
 }
 
-#[derive(Debug, PartialEq, PartialOrd, Clone)]
+#[derive(PartialEq, Clone)]
 pub enum Value {
     Int(i32),
+    BigInt(BigInt),
+    Rational(BigRational),
+    Decimal(Decimal),
+    Float(f64),
+    Complex(Complex64),
+    Quantity(Quantity),
     String(String),
+    // 要素を共有するため、代入や引数で渡したリストへの変更は元のリストにも反映される
+    List(Rc<RefCell<Vec<Value>>>),
+    // リストと同じく要素を共有する。キーは文字列で、キーの順に並ぶ
+    Map(Rc<RefCell<BTreeMap<String, Value>>>),
+    Struct(Rc<RefCell<Record>>),
     Bool(bool),
+    Function(Box<Declaration>),
     Unit,
 }
+impl Value {
+    /// 要素からリストを作成する
+    pub fn list(items: Vec<Value>) -> Value { Value::List(Rc::new(RefCell::new(items))) }
+
+    /// キーと値の組から辞書を作成する
+    pub fn map(entries: BTreeMap<String, Value>) -> Value { Value::Map(Rc::new(RefCell::new(entries))) }
+
+    /// リストや辞書の要素として表示する。文字列は区切りと見分けられるように引用符をつける
+    fn repr(&self) -> String {
+        match self {
+            Value::String(s) => format!("{:?}", s),
+            v => v.to_string(),
+        }
+    }
+
+    ///
+    /// 多倍長整数から値を作成する
+    ///
+    /// i32 に収まる場合は Int とし、BigInt は Int の範囲外の値だけを表すようにする
+    ///
+    pub fn from_bigint(n: BigInt) -> Value {
+        match n.to_i32() {
+            Some(i) => Value::Int(i),
+            None => Value::BigInt(n),
+        }
+    }
+
+    ///
+    /// 有理数から値を作成する
+    ///
+    /// 分母が 1 の場合は整数とし、Rational は整数でない値だけを表すようにする
+    ///
+    pub fn from_rational(r: BigRational) -> Value {
+        if r.is_integer() {
+            Value::from_bigint(r.to_integer())
+        } else {
+            Value::Rational(r)
+        }
+    }
+}
 impl AsBool for Value {
     fn as_bool(&self) -> bool {
         match self {
             Value::Int(i) if *i == 0 => false,
             Value::Int(_i) => true,
+            Value::BigInt(_n) => true,
+            Value::Rational(_r) => true,
+            Value::Decimal(d) => !d.is_zero(),
+            Value::Float(f) => *f != 0.0,
+            Value::Complex(c) => !c.is_zero(),
+            Value::Quantity(q) => q.value != 0.0,
             Value::String(_s) => true,
+            Value::List(l) => !l.borrow().is_empty(),
+            Value::Map(m) => !m.borrow().is_empty(),
+            Value::Struct(_r) => true,
             Value::Bool(b) => *b,
+            Value::Function(_f) => true,
             Value::Unit => false,
         }
     }
 }
-impl ToString for Value {
-    fn to_string(&self) -> String {
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match self {
-            Value::Int(i) => format!("{}", i),
-            Value::String(s) => format!("{}", s),
-            Value::Bool(b) => format!("{}", b),
-            Value::Unit => format!(""),
+            Value::Int(i) => write!(f, "{}", i),
+            Value::BigInt(n) => write!(f, "{}", n),
+            Value::Rational(r) => write!(f, "{}", r),
+            Value::Decimal(d) => write!(f, "{}", d),
+            // 整数値でも浮動小数点数であることが分かるように表示する
+            Value::Float(n) if n.is_finite() && n.fract() == 0.0 && n.abs() < 1e16 => write!(f, "{:.1}", n),
+            Value::Float(n) => write!(f, "{}", n),
+            // 3+4i, 3-4i, 4i のように表示する
+            Value::Complex(c) if c.re == 0.0 && c.im != 0.0 => write!(f, "{}i", c.im),
+            Value::Complex(c) if c.im.is_sign_negative() => write!(f, "{}-{}i", c.re, -c.im),
+            Value::Complex(c) => write!(f, "{}+{}i", c.re, c.im),
+            Value::Quantity(q) => write!(f, "{}", q),
+            Value::String(s) => write!(f, "{}", s),
+            Value::List(l) => visit(f, Rc::as_ptr(l) as *const (), "[...]", |f| {
+                let items: Vec<String> = l.borrow().iter().map(Value::repr).collect();
+                write!(f, "[{}]", items.join(", "))
+            }),
+            Value::Map(m) => visit(f, Rc::as_ptr(m) as *const (), "{...}", |f| {
+                let entries: Vec<String> = m.borrow().iter().map(|(k, v)| format!("{:?}: {}", k, v.repr())).collect();
+                write!(f, "{{{}}}", entries.join(", "))
+            }),
+            Value::Struct(r) => visit(f, Rc::as_ptr(r) as *const (), &format!("{} {{ ... }}", r.borrow().name), |f| {
+                let r = r.borrow();
+                let fields: Vec<String> = r.fields.iter().map(|(k, v)| format!("{}: {}", k, v.repr())).collect();
+                write!(f, "{} {{ {} }}", r.name, fields.join(", "))
+            }),
+            Value::Bool(b) => write!(f, "{}", b),
+            Value::Function(decl) => match &**decl {
+                Declaration::Function { arg, .. } => write!(f, "<fn({})>", arg.join(", ")),
+                Declaration::BuiltinFunction { id, .. } => write!(f, "<builtin {}>", id),
+            },
+            Value::Unit => Ok(()),
         }
     }
 }
 
+impl fmt::Debug for Value {
+    // リストや辞書は自身を含みうるため、表示中の値に再び出会った場合は省略する
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Value::Int(i) => f.debug_tuple("Int").field(i).finish(),
+            Value::BigInt(n) => f.debug_tuple("BigInt").field(n).finish(),
+            Value::Rational(r) => f.debug_tuple("Rational").field(r).finish(),
+            Value::Decimal(d) => f.debug_tuple("Decimal").field(d).finish(),
+            Value::Float(n) => f.debug_tuple("Float").field(n).finish(),
+            Value::Complex(c) => f.debug_tuple("Complex").field(c).finish(),
+            Value::Quantity(q) => f.debug_tuple("Quantity").field(q).finish(),
+            Value::String(s) => f.debug_tuple("String").field(s).finish(),
+            Value::List(l) => visit(f, Rc::as_ptr(l) as *const (), "List([...])", |f| f.debug_tuple("List").field(&*l.borrow()).finish()),
+            Value::Map(m) => visit(f, Rc::as_ptr(m) as *const (), "Map({...})", |f| f.debug_tuple("Map").field(&*m.borrow()).finish()),
+            Value::Struct(r) => visit(f, Rc::as_ptr(r) as *const (), "Struct(...)", |f| f.debug_tuple("Struct").field(&*r.borrow()).finish()),
+            Value::Bool(b) => f.debug_tuple("Bool").field(b).finish(),
+            Value::Function(decl) => f.debug_tuple("Function").field(decl).finish(),
+            Value::Unit => write!(f, "Unit"),
+        }
+    }
+}
+
+thread_local! {
+    // 表示中のリスト・辞書・構造体。自身を含む値を表示する時に循環を検出する
+    static VISITING: RefCell<Vec<*const ()>> = const { RefCell::new(Vec::new()) };
+}
+
+///
+/// 共有される値 ptr を body で表示する
+///
+/// ptr の表示中に ptr 自身を表示しようとした場合は循環しているため、代わりに omitted を表示する
+///
+fn visit(f: &mut fmt::Formatter, ptr: *const (), omitted: &str, body: impl FnOnce(&mut fmt::Formatter) -> fmt::Result) -> fmt::Result {
+    if VISITING.with_borrow(|visiting| visiting.contains(&ptr)) {
+        return write!(f, "{}", omitted);
+    }
+    VISITING.with_borrow_mut(|visiting| visiting.push(ptr));
+    let result = body(f);
+    VISITING.with_borrow_mut(|visiting| visiting.pop());
+    result
+}
+
+///
+/// 構造体の値
+///
+/// * name - 型名
+/// * fields - 宣言された順のフィールド名と値
+///
 #[derive(Debug, PartialEq, Clone)]
+pub struct Record {
+    pub name: String,
+    pub fields: Vec<(String, Value)>,
+}
+
+///
+/// 組み込み関数
+///
+/// Context を通じて引数で受け取った関数を呼び出せる
+///
+pub type BuiltinFn = fn(&mut Context, Vec<Value>) -> Result<Value, String>;
+
+///
+/// 関数の宣言
+///
+/// * Function - ユーザー定義関数。env は定義時の環境で、呼び出し時のスコープの外側になる
+/// * BuiltinFunction - 組み込み関数
+///
+#[derive(Clone)]
 pub enum Declaration {
     Function {
         arg: Vec<String>,
-        st: Box<Statement>,
+        st: Rc<Statement>,
+        env: Env,
     },
     BuiltinFunction {
         id: String,
-        r#fn: fn(Vec<Value>) -> Result<Value, String>,
+        r#fn: BuiltinFn,
     },
 }
+impl PartialEq for Declaration {
+    // 関数ポインタの比較は当てにならないため、組み込み関数は名前で比較する
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (Declaration::Function { arg: a1, st: s1, env: e1 }, Declaration::Function { arg: a2, st: s2, env: e2 }) => a1 == a2 && s1 == s2 && e1 == e2,
+            (Declaration::BuiltinFunction { id: i1, .. }, Declaration::BuiltinFunction { id: i2, .. }) => i1 == i2,
+            _ => false,
+        }
+    }
+}
+impl fmt::Debug for Declaration {
+    // 定義時の環境は自身を含みうるため表示しない
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Declaration::Function { arg, st, .. } => f.debug_struct("Function").field("arg", arg).field("st", st).finish_non_exhaustive(),
+            Declaration::BuiltinFunction { id, r#fn } => f.debug_struct("BuiltinFunction").field("id", id).field("fn", r#fn).finish(),
+        }
+    }
+}
 
 #[derive(Debug, PartialEq, Clone)]
 pub enum Syntax {
@@ -0,0 +1,151 @@
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::collections::HashSet;
+use std::fmt;
+use std::rc::Rc;
+
+use crate::enums::Settings;
+use crate::enums::TypeTable;
+use crate::enums::Value;
+
+///
+/// 1 つのスコープ
+///
+/// * vars - このスコープで定義された変数
+/// * globals - `global` 宣言された変数名
+/// * nonlocals - `nonlocal` 宣言された変数名
+/// * parent - 外側のスコープ（None は大域スコープ）
+/// * types - このスコープで宣言された構造体の型
+/// * settings - 実行時の設定（大域スコープのものだけを使う）
+///
+#[derive(Default)]
+struct Scope {
+    vars: HashMap<String, Value>,
+    types: TypeTable,
+    globals: HashSet<String>,
+    nonlocals: HashSet<String>,
+    parent: Option<Env>,
+    settings: Settings,
+}
+
+///
+/// スコープチェーンによる環境
+///
+/// clone しても同じスコープを指すため、関数はクロージャとして定義時の環境を保持できる
+///
+#[derive(Clone, Default)]
+pub struct Env(Rc<RefCell<Scope>>);
+
+impl Env {
+    /// 大域スコープを作成する
+    pub fn new() -> Self { Env::default() }
+
+    /// self を外側に持つスコープを作成する
+    pub fn child(&self) -> Self {
+        Env(Rc::new(RefCell::new(Scope {
+            parent: Some(self.clone()),
+            ..Scope::default()
+        })))
+    }
+
+    fn parent(&self) -> Option<Env> { self.0.borrow().parent.clone() }
+
+    fn root(&self) -> Env {
+        match self.parent() {
+            Some(parent) => parent.root(),
+            None => self.clone(),
+        }
+    }
+
+    ///
+    /// 変数の値を参照する
+    ///
+    /// 見つからなければ外側のスコープへ向かって探す
+    ///
+    pub fn get(&self, id: &str) -> Option<Value> {
+        let scope = self.0.borrow();
+        if scope.globals.contains(id) {
+            return self.root().0.borrow().vars.get(id).cloned();
+        }
+        match (scope.vars.get(id), &scope.parent) {
+            (Some(value), _) => Some(value.clone()),
+            (None, Some(parent)) => parent.get(id),
+            (None, None) => None,
+        }
+    }
+
+    /// このスコープに変数を定義する
+    pub fn define(&self, id: &str, value: Value) { self.0.borrow_mut().vars.insert(id.to_string(), value); }
+
+    ///
+    /// 変数に代入する
+    ///
+    /// * `global` 宣言された変数 - 大域スコープに代入する
+    /// * `nonlocal` 宣言された変数 - その変数を定義している最も内側の外側のスコープに代入する
+    /// * それ以外 - このスコープに定義する
+    ///
+    pub fn assign(&self, id: &str, value: Value) -> Result<(), String> {
+        let (global, nonlocal) = {
+            let scope = self.0.borrow();
+            (scope.globals.contains(id), scope.nonlocals.contains(id))
+        };
+        if global {
+            self.root().define(id, value);
+            return Ok(());
+        }
+        if nonlocal {
+            let mut scope = self.parent();
+            while let Some(env) = scope {
+                if env.0.borrow().vars.contains_key(id) {
+                    env.define(id, value);
+                    return Ok(());
+                }
+                scope = env.parent();
+            }
+            return Err(format!("nonlocal 宣言された変数 {:?} が外側のスコープに見つからない", id));
+        }
+        self.define(id, value);
+        Ok(())
+    }
+
+    /// このスコープに構造体の型を宣言する
+    pub fn define_type(&self, id: &str, fields: Vec<String>) { self.0.borrow_mut().types.insert(id.to_string(), fields); }
+
+    /// 構造体の型のフィールド名を返却する。見つからなければ外側のスコープへ向かって探す
+    pub fn get_type(&self, id: &str) -> Option<Vec<String>> {
+        let scope = self.0.borrow();
+        match (scope.types.get(id), &scope.parent) {
+            (Some(fields), _) => Some(fields.clone()),
+            (None, Some(parent)) => parent.get_type(id),
+            (None, None) => None,
+        }
+    }
+
+    /// 変数名を `global` 宣言する
+    pub fn declare_global(&self, id: &str) { self.0.borrow_mut().globals.insert(id.to_string()); }
+
+    /// 変数名を `nonlocal` 宣言する
+    pub fn declare_nonlocal(&self, id: &str) -> Result<(), String> {
+        if self.parent().is_none() {
+            return Err(format!("大域スコープで変数 {:?} を nonlocal 宣言することはできない", id));
+        }
+        self.0.borrow_mut().nonlocals.insert(id.to_string());
+        Ok(())
+    }
+
+    /// 実行時の設定を返却する
+    pub fn settings(&self) -> Settings { self.root().0.borrow().settings.clone() }
+
+    /// 実行時の設定を変更する
+    pub fn update_settings<T>(&self, f: impl FnOnce(&mut Settings) -> T) -> T { f(&mut self.root().0.borrow_mut().settings) }
+}
+
+impl PartialEq for Env {
+    // 同じスコープを指しているかどうかで比較する
+    fn eq(&self, other: &Self) -> bool { Rc::ptr_eq(&self.0, &other.0) }
+}
+
+impl fmt::Debug for Env {
+    // 外側のスコープやクロージャの環境を辿ると循環しうるため、このスコープの変数だけを表示する
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result { f.debug_map().entries(self.0.borrow().vars.iter()).finish() }
+}
@@ -1,150 +1,487 @@
-mod builtin;
+pub(crate) mod builtin;
+mod operator;
 
 use crate::Env;
 use crate::FunctionTable;
 
+use std::cell::Cell;
+use std::cell::RefCell;
+use std::collections::BTreeMap;
+use std::rc::Rc;
+
+use num_complex::Complex64;
+
 use crate::enums::AsBool;
 use crate::enums::BinOp;
+use crate::enums::BuiltinFn;
 use crate::enums::ComparisonOp;
 use crate::enums::Declaration;
 use crate::enums::Expr;
+use crate::enums::ExprKind;
+use crate::enums::Flow;
+use crate::enums::LogicalOp;
+use crate::enums::Record;
+use crate::enums::RuntimeError;
+use crate::enums::Settings;
+use crate::enums::Span;
 use crate::enums::Statement;
+use crate::enums::StatementKind;
 use crate::enums::Syntax;
 use crate::enums::Value;
+use crate::unit::Unit;
+
+/// 関数呼び出しの深さの上限。これを超える再帰はスタックを使い果たす前に実行時エラーにする
+const MAX_CALL_DEPTH: usize = 10000;
+
+thread_local! {
+    // 実行中の関数呼び出しの深さ
+    static CALL_DEPTH: Cell<usize> = const { Cell::new(0) };
+}
 
-pub fn eval(syntax: Syntax, env: &mut Env, ft: &mut FunctionTable) -> () {
+pub fn eval(syntax: Syntax, env: &Env, ft: &mut FunctionTable) -> Result<(), RuntimeError> {
     // 準備
     builtin::register(ft);
 
     match syntax {
-        Syntax::Statement(st) => match exec(st, env, ft) {
-            Ok(..) => (),
-            Err(error) => println!("エラーが発生しました。{:?}", error),
-        },
+        Syntax::Statement(st) => {
+            // 最上位での return は実行の終了として扱う
+            exec(&st, env, ft)?;
+            Ok(())
+        }
     }
 }
 
-// 文を実行する
-fn exec(statement: Statement, env: &mut Env, ft: &mut FunctionTable) -> Result<(), String> {
-    match statement {
-        Statement::CompoundStatement { st1, st2 } => {
-            exec(*st1, env, ft)?;
-            exec(*st2, env, ft)?;
-            Ok(())
-        }
-        Statement::FunctionDefine { id, arg, st } => {
-            ft.insert(id, Declaration::Function { arg, st });
-            Ok(())
+/// span の位置で発生したエラーを作成する
+fn error(span: Span, message: String) -> RuntimeError { RuntimeError { message, span } }
+
+// 文を実行し、その後の制御の流れを返却する
+//
+// 再帰の深さはスタックの大きさで制限されるため、文の種類ごとの処理は関数に分けて exec 自体のフレームを小さく保つ
+fn exec(statement: &Statement, env: &Env, ft: &mut FunctionTable) -> Result<Flow, RuntimeError> {
+    let span = statement.span;
+    match &statement.kind {
+        StatementKind::CompoundStatement { st1, st2 } => match exec(st1, env, ft)? {
+            Flow::Normal => exec(st2, env, ft),
+            flow => Ok(flow),
+        },
+        StatementKind::FunctionDefine { id, arg, st } => {
+            define_function(id, arg, st, env);
+            Ok(Flow::Normal)
         }
-        Statement::FunctionCall { expr } => {
+        StatementKind::StructDefine { id, fields } => define_struct(id, fields, span, env),
+        StatementKind::FunctionCall { expr } => {
             calc(expr, env, ft)?;
-            Ok(())
+            Ok(Flow::Normal)
         }
-        Statement::Return { expr } => {
-            let value = calc(*expr, env, ft)?;
-            env.insert("return".to_string(), value);
-            Ok(())
-        }
-        Statement::Assign { id, e } => {
-            let value = calc(*e, env, ft)?;
-            env.insert(id, value);
-            Ok(())
-        }
-        Statement::If {
+        StatementKind::While { condition, body } => exec_while(condition, body, env, ft),
+        StatementKind::For { id, start, end, body } => exec_for(id, start, end, body, span, env, ft),
+        StatementKind::Break => Ok(Flow::Break),
+        StatementKind::Continue => Ok(Flow::Continue),
+        StatementKind::Return { expr } => Ok(Flow::Return(calc(expr, env, ft)?)),
+        StatementKind::Assign { id, path, e } => exec_assign(id, path, e, span, env, ft),
+        StatementKind::If {
             condition,
             then,
             els,
-        } => {
-            if calc(*condition, env, ft)?.as_bool() {
-                exec(*then, env, ft)
-            } else {
-                exec(*els, env, ft)
+        } => exec_if(condition, then, els.as_deref(), env, ft),
+        StatementKind::Global { ids } => {
+            for id in ids {
+                env.declare_global(id);
             }
+            Ok(Flow::Normal)
         }
-        _ => Err(format!(
-            "実行できない Statement {:?} を実行しようとした",
-            statement
-        )),
+        StatementKind::Nonlocal { ids } => declare_nonlocal(ids, span, env),
+        StatementKind::Pragma { name, value } => exec_pragma(name, value.as_deref(), span, env, ft),
+        StatementKind::Null => Ok(Flow::Normal),
     }
 }
 
-// 式を計算する
-fn calc(expr: Expr, env: &mut Env, ft: &mut FunctionTable) -> Result<Value, String> {
-    match expr {
-        Expr::Binary { op, lhs, rhs } => match op {
-            BinOp::Add => match (calc(*lhs, env, ft)?, calc(*rhs, env, ft)?) {
-                (Value::Int(lhs_n), Value::Int(rhs_n)) => Ok(Value::Int(lhs_n + rhs_n)),
-                (Value::String(lhs_s), Value::String(rhs_s)) => Ok(Value::String(lhs_s + &rhs_s)),
-                _ => Err(format!("加算処理が未定義")),
-            },
-            BinOp::Sub => match (calc(*lhs, env, ft)?, calc(*rhs, env, ft)?) {
-                (Value::Int(lhs_n), Value::Int(rhs_n)) => Ok(Value::Int(lhs_n - rhs_n)),
-                _ => Err(format!("減算処理が未定義")),
-            },
-            BinOp::Mul => match (calc(*lhs, env, ft)?, calc(*rhs, env, ft)?) {
-                (Value::Int(lhs_n), Value::Int(rhs_n)) => Ok(Value::Int(lhs_n * rhs_n)),
-                _ => Err(format!("乗算処理が未定義")),
-            },
-            BinOp::Div => match (calc(*lhs, env, ft)?, calc(*rhs, env, ft)?) {
-                (Value::Int(lhs_n), Value::Int(rhs_n)) if rhs_n == 0 => Err(format!("{} / {} の0での割り算が発生した", lhs_n, rhs_n)),
-                (Value::Int(lhs_n), Value::Int(rhs_n)) => Ok(Value::Int(lhs_n / rhs_n)),
-                _ => Err(format!("除算処理が未定義")),
-            },
-        },
-        Expr::Comparison { op, lhs, rhs } => match op {
-            ComparisonOp::Lt => match (calc(*lhs, env, ft)?, calc(*rhs, env, ft)?) {
-                (Value::Int(lhs_n), Value::Int(rhs_n)) => Ok(Value::Bool(lhs_n < rhs_n)),
-                _ => Err(format!("比較が未定義")),
-            },
-            ComparisonOp::Gt => match (calc(*lhs, env, ft)?, calc(*rhs, env, ft)?) {
-                (Value::Int(lhs_n), Value::Int(rhs_n)) => Ok(Value::Bool(lhs_n > rhs_n)),
-                _ => Err(format!("比較が未定義")),
-            },
-            ComparisonOp::Eq => match (calc(*lhs, env, ft)?, calc(*rhs, env, ft)?) {
-                (Value::Int(lhs_n), Value::Int(rhs_n)) => Ok(Value::Bool(lhs_n == rhs_n)),
-                _ => Err(format!("比較が未定義")),
-            },
-        },
-        Expr::Number(n) => Ok(Value::Int(n)),
-        Expr::Var(s) => match env.get(&s.to_string()) {
-            Some(num) => Ok(num.clone()),
-            None => Err(format!(
-                "環境 env {:?} に変数 {:?} が登録されていない",
-                env, s
-            )),
-        },
-        Expr::Str(s) => Ok(Value::String(s)),
-        Expr::FunctionCall { id, args } => {
-            // ローカル環境を用意する
-            let mut local_env = Env::new();
-
-            match ft.get(&id.to_string()) {
-                Some(Declaration::BuiltinFunction { id: _, r#fn }) => {
-                    // 組み込み関数を実行する
-                    r#fn(
-                        args.iter()
-                            .map(|x| calc(x.clone(), env, &mut ft.clone()).unwrap())
-                            .collect(),
-                    )
-                }
-                Some(Declaration::Function { arg, st }) => {
-                    // 引数として渡した値をセットする
-                    for (expr, param) in args.iter().zip(arg.clone().iter()) {
-                        local_env.insert(param.to_string(), calc(expr.clone(), env, &mut ft.clone())?);
-                    }
-
-                    // 関数を実行する
-                    exec(*st.clone(), &mut local_env, &mut ft.clone())?;
-                    match local_env.get(&"return".to_string()) {
-                        Some(i) => Ok(i.clone()),
-                        None => todo!("関数が値を返さない場合の挙動が未定義"),
-                    }
-                }
-                None => Err(format!(
-                    "関数テーブル ft {:?} に関数名 {:?} が登録されていない",
-                    ft, id
-                )),
+/// 定義時の環境をクロージャとして保持し、関数の値としてこのスコープに定義する
+#[inline(never)]
+fn define_function(id: &str, arg: &[String], st: &Rc<Statement>, env: &Env) {
+    let decl = Declaration::Function {
+        arg: arg.to_vec(),
+        st: st.clone(),
+        env: env.clone(),
+    };
+    env.define(id, Value::Function(Box::new(decl)));
+}
+
+#[inline(never)]
+fn define_struct(id: &str, fields: &[String], span: Span, env: &Env) -> Result<Flow, RuntimeError> {
+    if let Some(field) = fields.iter().enumerate().find_map(|(i, f)| fields[..i].contains(f).then_some(f)) {
+        return Err(error(span, format!("構造体 {} のフィールド {} が重複している", id, field)));
+    }
+    env.define_type(id, fields.to_vec());
+    Ok(Flow::Normal)
+}
+
+#[inline(never)]
+fn exec_while(condition: &Expr, body: &Statement, env: &Env, ft: &mut FunctionTable) -> Result<Flow, RuntimeError> {
+    while calc(condition, env, ft)?.as_bool() {
+        match exec(body, env, ft)? {
+            Flow::Normal | Flow::Continue => {}
+            Flow::Break => break,
+            flow => return Ok(flow),
+        }
+    }
+    Ok(Flow::Normal)
+}
+
+#[inline(never)]
+fn exec_for(id: &str, start: &Expr, end: &Expr, body: &Statement, span: Span, env: &Env, ft: &mut FunctionTable) -> Result<Flow, RuntimeError> {
+    let (from, to) = match (calc(start, env, ft)?, calc(end, env, ft)?) {
+        (Value::Int(from), Value::Int(to)) => (from, to),
+        _ => return Err(error(span, "for 文の範囲に整数以外が指定された".to_string())),
+    };
+    for i in from..to {
+        env.assign(id, Value::Int(i)).map_err(|message| error(span, message))?;
+        match exec(body, env, ft)? {
+            Flow::Normal | Flow::Continue => {}
+            Flow::Break => break,
+            flow => return Ok(flow),
+        }
+    }
+    Ok(Flow::Normal)
+}
+
+#[inline(never)]
+fn exec_assign(id: &str, path: &[Expr], e: &Expr, span: Span, env: &Env, ft: &mut FunctionTable) -> Result<Flow, RuntimeError> {
+    let value = calc(e, env, ft)?;
+    match path.split_last() {
+        None => env.assign(id, value).map_err(|message| error(span, message))?,
+        Some((last, init)) => {
+            // リストや辞書は要素を共有しているため、取り出した入れ物を書き換えれば変数にも反映される
+            let mut target = match env.get(id) {
+                Some(target) => target,
+                None => return Err(error(span, format!("変数 {:?} が登録されていない", id))),
+            };
+            for key in init {
+                let key = calc(key, env, ft)?;
+                target = operator::index(target, key).map_err(|message| error(span, message))?;
             }
+            let key = calc(last, env, ft)?;
+            operator::set_index(target, key, value).map_err(|message| error(span, message))?;
+        }
+    }
+    Ok(Flow::Normal)
+}
+
+#[inline(never)]
+fn exec_if(condition: &Expr, then: &Statement, els: Option<&Statement>, env: &Env, ft: &mut FunctionTable) -> Result<Flow, RuntimeError> {
+    if calc(condition, env, ft)?.as_bool() {
+        exec(then, env, ft)
+    } else {
+        match els {
+            Some(els) => exec(els, env, ft),
+            None => Ok(Flow::Normal),
+        }
+    }
+}
+
+#[inline(never)]
+fn declare_nonlocal(ids: &[String], span: Span, env: &Env) -> Result<Flow, RuntimeError> {
+    for id in ids {
+        env.declare_nonlocal(id).map_err(|message| error(span, message))?;
+    }
+    Ok(Flow::Normal)
+}
+
+#[inline(never)]
+fn exec_pragma(name: &str, value: Option<&Expr>, span: Span, env: &Env, ft: &mut FunctionTable) -> Result<Flow, RuntimeError> {
+    let value = match value {
+        Some(value) => calc(value, env, ft)?,
+        None => Value::Bool(true),
+    };
+    env.update_settings(|settings| settings.set(name, value)).map_err(|message| error(span, message))?;
+    Ok(Flow::Normal)
+}
+
+// 式を計算する
+//
+// exec と同じく、式の種類ごとの処理は関数に分けて calc 自体のフレームを小さく保つ
+fn calc(expr: &Expr, env: &Env, ft: &mut FunctionTable) -> Result<Value, RuntimeError> {
+    let span = expr.span;
+    match &expr.kind {
+        ExprKind::Binary { op, lhs, rhs } => calc_binary(op, lhs, rhs, span, env, ft),
+        ExprKind::Comparison { lhs, rest } => calc_comparison(lhs, rest, env, ft),
+        ExprKind::Logical { op, lhs, rhs } => calc_logical(op, lhs, rhs, env, ft),
+        ExprKind::Not(operand) | ExprKind::Neg(operand) | ExprKind::BitNot(operand) => calc_unary(&expr.kind, operand, span, env, ft),
+        ExprKind::Bool(b) => Ok(Value::Bool(*b)),
+        ExprKind::Number(_) | ExprKind::BigNumber(_) | ExprKind::Float(_) | ExprKind::Decimal(_) | ExprKind::Imaginary(_) | ExprKind::Str(_) => Ok(literal(&expr.kind, env)),
+        ExprKind::Quantity { value, unit } => calc_quantity(value, unit, span, env, ft),
+        ExprKind::Var(s) => calc_var(s, span, env, ft),
+        ExprKind::List(items) => calc_list(items, env, ft),
+        ExprKind::Map(entries) => calc_map(entries, env, ft),
+        ExprKind::Struct { name, fields } => calc_struct(name, fields, span, env, ft),
+        ExprKind::Index { target, index } => calc_index(target, index, span, env, ft),
+        ExprKind::Slice { target, start, end } => calc_slice(target, start.as_deref(), end.as_deref(), span, env, ft),
+        ExprKind::Lambda { arg, body } => Ok(lambda(arg, body, env)),
+        ExprKind::If { condition, then, els } => calc_if(condition, then, els.as_deref(), env, ft),
+        ExprKind::FunctionCall { callee, args } => calc_call(callee, args, span, env, ft),
+    }
+}
+
+#[inline(never)]
+fn calc_binary(op: &BinOp, lhs: &Expr, rhs: &Expr, span: Span, env: &Env, ft: &mut FunctionTable) -> Result<Value, RuntimeError> {
+    let (lhs, rhs) = (calc(lhs, env, ft)?, calc(rhs, env, ft)?);
+    operator::binary(op, lhs, rhs, &env.settings()).map_err(|message| error(span, message))
+}
+
+// 比較が連結されている場合は、隣り合う組をすべて満たすかどうかを調べる
+#[inline(never)]
+fn calc_comparison(lhs: &Expr, rest: &[(ComparisonOp, Expr)], env: &Env, ft: &mut FunctionTable) -> Result<Value, RuntimeError> {
+    let (mut left, mut left_span) = (calc(lhs, env, ft)?, lhs.span);
+    for (op, rhs) in rest {
+        let right = calc(rhs, env, ft)?;
+        let result = operator::compare(op, left, right.clone()).map_err(|message| error(left_span.to(rhs.span), message))?;
+        if !result.as_bool() {
+            return Ok(result);
         }
+        (left, left_span) = (right, rhs.span);
+    }
+    Ok(Value::Bool(true))
+}
+
+// 左辺だけで結果が決まる場合は右辺を評価しない
+#[inline(never)]
+fn calc_logical(op: &LogicalOp, lhs: &Expr, rhs: &Expr, env: &Env, ft: &mut FunctionTable) -> Result<Value, RuntimeError> {
+    let settings = env.settings();
+    let truth = |value: Value, span: Span| operator::truth(&value, &settings).map_err(|message| error(span, message));
+    let result = match (op, truth(calc(lhs, env, ft)?, lhs.span)?) {
+        (LogicalOp::And, false) => false,
+        (LogicalOp::Or, true) => true,
+        _ => truth(calc(rhs, env, ft)?, rhs.span)?,
+    };
+    Ok(Value::Bool(result))
+}
+
+#[inline(never)]
+fn calc_unary(kind: &ExprKind, operand: &Expr, span: Span, env: &Env, ft: &mut FunctionTable) -> Result<Value, RuntimeError> {
+    let value = calc(operand, env, ft)?;
+    match kind {
+        ExprKind::Not(_) => Ok(Value::Bool(!operator::truth(&value, &env.settings()).map_err(|message| error(operand.span, message))?)),
+        ExprKind::Neg(_) => operator::negate(value, &env.settings()).map_err(|message| error(span, message)),
+        _ => operator::bit_not(value, &env.settings()).map_err(|message| error(span, message)),
+    }
+}
+
+#[inline(never)]
+fn literal(kind: &ExprKind, env: &Env) -> Value {
+    match kind {
+        // 整数のリテラルも演算結果と同じく固定幅の設定で折り返す
+        ExprKind::Number(n) => operator::wrap(Value::Int(*n), &env.settings()),
+        ExprKind::BigNumber(n) => operator::wrap(Value::BigInt(n.clone()), &env.settings()),
+        ExprKind::Float(n) => Value::Float(*n),
+        ExprKind::Decimal(n) => Value::Decimal(*n),
+        ExprKind::Imaginary(n) => Value::Complex(Complex64::new(0.0, *n)),
+        ExprKind::Str(s) => Value::String(s.clone()),
+        _ => unreachable!(),
+    }
+}
+
+#[inline(never)]
+fn calc_quantity(value: &Expr, unit: &Unit, span: Span, env: &Env, ft: &mut FunctionTable) -> Result<Value, RuntimeError> {
+    // 単位つきの量の演算は折り返さないため、数値も折り返さずに使う
+    let value = match &value.kind {
+        ExprKind::Number(n) => Value::Int(*n),
+        ExprKind::BigNumber(n) => Value::BigInt(n.clone()),
+        _ => calc(value, env, ft)?,
+    };
+    operator::with_unit(value, unit).map_err(|message| error(span, message))
+}
+
+#[inline(never)]
+fn calc_var(s: &str, span: Span, env: &Env, ft: &FunctionTable) -> Result<Value, RuntimeError> {
+    match (env.get(s), ft.get(s)) {
+        (Some(value), _) => Ok(value),
+        // 組み込み関数の名前は関数の値として参照できる
+        (None, Some(decl)) => Ok(Value::Function(Box::new(decl.clone()))),
+        // 変数や関数として定義されていない名前は組み込みの定数を参照する
+        (None, None) => builtin::constant(s).ok_or_else(|| error(span, format!("変数 {:?} が登録されていない", s))),
+    }
+}
+
+#[inline(never)]
+fn calc_list(items: &[Expr], env: &Env, ft: &mut FunctionTable) -> Result<Value, RuntimeError> {
+    let mut values = Vec::new();
+    for item in items {
+        values.push(calc(item, env, ft)?);
+    }
+    Ok(Value::list(values))
+}
+
+#[inline(never)]
+fn calc_map(entries: &[(String, Expr)], env: &Env, ft: &mut FunctionTable) -> Result<Value, RuntimeError> {
+    let mut values = BTreeMap::new();
+    for (key, value) in entries {
+        values.insert(key.clone(), calc(value, env, ft)?);
+    }
+    Ok(Value::map(values))
+}
+
+#[inline(never)]
+fn calc_struct(name: &str, fields: &[(String, Expr)], span: Span, env: &Env, ft: &mut FunctionTable) -> Result<Value, RuntimeError> {
+    let declared = match env.get_type(name) {
+        Some(declared) => declared,
+        None => return Err(error(span, format!("構造体 {} が宣言されていない", name))),
+    };
+    if let Some((field, _)) = fields.iter().find(|(field, _)| !declared.contains(field)) {
+        return Err(error(span, format!("構造体 {} にフィールド {} は存在しない", name, field)));
+    }
+
+    // フィールドは宣言された順に並べる
+    let mut values = Vec::new();
+    for field in declared {
+        let mut exprs = fields.iter().filter(|(f, _)| *f == field);
+        match (exprs.next(), exprs.next()) {
+            (Some((_, expr)), None) => values.push((field, calc(expr, env, ft)?)),
+            (Some(_), Some(_)) => return Err(error(span, format!("構造体 {} のフィールド {} が重複している", name, field))),
+            (None, _) => return Err(error(span, format!("構造体 {} のフィールド {} が指定されていない", name, field))),
+        }
+    }
+    Ok(Value::Struct(Rc::new(RefCell::new(Record { name: name.to_string(), fields: values }))))
+}
+
+#[inline(never)]
+fn calc_index(target: &Expr, index: &Expr, span: Span, env: &Env, ft: &mut FunctionTable) -> Result<Value, RuntimeError> {
+    let (target, index) = (calc(target, env, ft)?, calc(index, env, ft)?);
+    operator::index(target, index).map_err(|message| error(span, message))
+}
+
+#[inline(never)]
+fn calc_slice(target: &Expr, start: Option<&Expr>, end: Option<&Expr>, span: Span, env: &Env, ft: &mut FunctionTable) -> Result<Value, RuntimeError> {
+    let target = calc(target, env, ft)?;
+    let start = match start {
+        Some(start) => Some(calc(start, env, ft)?),
+        None => None,
+    };
+    let end = match end {
+        Some(end) => Some(calc(end, env, ft)?),
+        None => None,
+    };
+    operator::slice(target, start, end).map_err(|message| error(span, message))
+}
+
+// 定義時の環境とともに保持する
+#[inline(never)]
+fn lambda(arg: &[String], body: &Rc<Statement>, env: &Env) -> Value {
+    Value::Function(Box::new(Declaration::Function {
+        arg: arg.to_vec(),
+        st: body.clone(),
+        env: env.clone(),
+    }))
+}
+
+#[inline(never)]
+fn calc_if(condition: &Expr, then: &Expr, els: Option<&Expr>, env: &Env, ft: &mut FunctionTable) -> Result<Value, RuntimeError> {
+    if calc(condition, env, ft)?.as_bool() {
+        calc(then, env, ft)
+    } else {
+        match els {
+            Some(els) => calc(els, env, ft),
+            None => Ok(Value::Unit),
+        }
+    }
+}
+
+#[inline(never)]
+fn calc_call(callee: &Expr, args: &[Expr], span: Span, env: &Env, ft: &mut FunctionTable) -> Result<Value, RuntimeError> {
+    let callee = calc(callee, env, ft)?;
+    let mut values = Vec::new();
+    for arg in args {
+        values.push(calc(arg, env, ft)?);
+    }
+    call(callee, values, span, ft, &env.settings())
+}
+
+///
+/// 組み込み関数から関数を呼び出すための文脈
+///
+/// * ft - 呼び出し元の関数テーブル
+/// * span - 組み込み関数を呼び出した位置
+/// * settings - 呼び出した時点の実行時の設定
+/// * error - 呼び出した関数の中で発生したエラー
+///
+pub struct Context<'a> {
+    ft: &'a mut FunctionTable,
+    span: Span,
+    settings: Settings,
+    error: Option<RuntimeError>,
+}
+
+impl Context<'_> {
+    /// 呼び出した時点の実行時の設定
+    pub fn settings(&self) -> &Settings { &self.settings }
+
+    ///
+    /// 関数 f を args を引数として呼び出す
+    ///
+    /// 呼び出した関数の中でエラーが発生した場合は、そのエラーを覚えておき
+    /// 組み込み関数がエラーを返した時に元の位置のまま報告する
+    ///
+    pub fn call(&mut self, f: &Value, args: Vec<Value>) -> Result<Value, String> {
+        call(f.clone(), args, self.span, self.ft, &self.settings).map_err(|e| {
+            let message = e.message.clone();
+            self.error.get_or_insert(e);
+            message
+        })
+    }
+}
+
+///
+/// 関数を呼び出す
+///
+/// * callee - 呼び出す値。関数以外の場合はエラーになる
+/// * args - 評価済みの引数
+/// * settings - 組み込み関数に渡す実行時の設定
+///
+fn call(callee: Value, args: Vec<Value>, span: Span, ft: &mut FunctionTable, settings: &Settings) -> Result<Value, RuntimeError> {
+    match callee {
+        Value::Function(decl) => match decl.as_ref() {
+            Declaration::BuiltinFunction { id: _, r#fn } => call_builtin(*r#fn, args, span, ft, settings),
+            Declaration::Function { arg, st, env: closure } => call_function(arg, st, closure, args, span, ft),
+        },
+        value => Err(error(span, format!("関数ではない値 {} を呼び出そうとした", value))),
+    }
+}
+
+// 組み込み関数を実行する
+#[inline(never)]
+fn call_builtin(r#fn: BuiltinFn, args: Vec<Value>, span: Span, ft: &mut FunctionTable, settings: &Settings) -> Result<Value, RuntimeError> {
+    let mut context = Context {
+        ft,
+        span,
+        settings: settings.clone(),
+        error: None,
+    };
+    r#fn(&mut context, args).map_err(|message| context.error.take().unwrap_or_else(|| error(span, message)))
+}
+
+#[inline(never)]
+fn call_function(arg: &[String], st: &Statement, closure: &Env, args: Vec<Value>, span: Span, ft: &mut FunctionTable) -> Result<Value, RuntimeError> {
+    if arg.len() != args.len() {
+        return Err(error(span, format!("{} 個の引数を受け取る関数に {} 個の引数が渡された", arg.len(), args.len())));
+    }
+
+    // 定義時の環境の内側にローカル環境を用意し、引数として渡した値をセットする
+    let local_env = closure.child();
+    for (param, value) in arg.iter().zip(args) {
+        local_env.define(param, value);
+    }
+
+    let depth = CALL_DEPTH.get();
+    if depth >= MAX_CALL_DEPTH {
+        return Err(error(span, format!("関数呼び出しの深さが上限の {} を超えた", MAX_CALL_DEPTH)));
+    }
+
+    // 関数を実行する。return しなかった場合は Unit を返す
+    // （break と continue が関数の外へ出ないことはパース時に確認済み）
+    CALL_DEPTH.set(depth + 1);
+    let flow = exec(st, &local_env, ft);
+    CALL_DEPTH.set(depth);
+    match flow? {
+        Flow::Return(value) => Ok(value),
+        Flow::Normal | Flow::Break | Flow::Continue => Ok(Value::Unit),
     }
 }
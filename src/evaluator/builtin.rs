@@ -1,15 +1,35 @@
+mod functional;
+mod math;
+mod string;
+
+use num_bigint::BigInt;
+use num_complex::Complex64;
+use num_integer::Integer;
+use num_rational::BigRational;
+use num_traits::FromPrimitive;
+use num_traits::Signed;
+use num_traits::ToPrimitive;
+use num_traits::Zero;
+use rust_decimal::Decimal;
+
+use super::operator;
+use super::Context;
+
+use crate::enums::BuiltinFn;
 use crate::enums::Declaration;
+use crate::enums::Rounding;
 use crate::enums::Value;
+use crate::unit;
 use crate::FunctionTable;
 
 #[derive(Clone)]
 struct Function<'a> {
     id: &'a str,
-    r#fn: fn(Vec<Value>) -> Result<Value, String>,
+    r#fn: BuiltinFn,
 }
 
 // 組み込み関数の一覧
-static BUILTIN_LIST: [Function; 3] = [
+static BUILTIN_LIST: [Function; 68] = [
     Function {
         id: "print_int",
         r#fn: print,
@@ -22,12 +42,272 @@ static BUILTIN_LIST: [Function; 3] = [
         id: "print",
         r#fn: print,
     },
+    Function {
+        id: "int",
+        r#fn: int,
+    },
+    Function {
+        id: "float",
+        r#fn: float,
+    },
+    Function {
+        id: "rational",
+        r#fn: rational,
+    },
+    Function {
+        id: "fixed",
+        r#fn: fixed,
+    },
+    Function {
+        id: "decimal",
+        r#fn: decimal,
+    },
+    Function {
+        id: "hex",
+        r#fn: hex,
+    },
+    Function {
+        id: "bin",
+        r#fn: bin,
+    },
+    Function {
+        id: "oct",
+        r#fn: oct,
+    },
+    Function {
+        id: "round",
+        r#fn: math::round,
+    },
+    Function {
+        id: "scale",
+        r#fn: scale,
+    },
+    Function {
+        id: "re",
+        r#fn: re,
+    },
+    Function {
+        id: "im",
+        r#fn: im,
+    },
+    Function {
+        id: "abs",
+        r#fn: math::abs,
+    },
+    Function {
+        id: "arg",
+        r#fn: arg,
+    },
+    Function {
+        id: "conj",
+        r#fn: conj,
+    },
+    Function {
+        id: "polar",
+        r#fn: polar,
+    },
+    Function {
+        id: "to",
+        r#fn: to,
+    },
+    Function {
+        id: "len",
+        r#fn: len,
+    },
+    Function {
+        id: "push",
+        r#fn: push,
+    },
+    Function {
+        id: "pop",
+        r#fn: pop,
+    },
+    Function {
+        id: "reverse",
+        r#fn: reverse,
+    },
+    Function {
+        id: "sort",
+        r#fn: sort,
+    },
+    Function {
+        id: "keys",
+        r#fn: keys,
+    },
+    Function {
+        id: "values",
+        r#fn: values,
+    },
+    Function {
+        id: "has",
+        r#fn: has,
+    },
+    Function {
+        id: "remove",
+        r#fn: remove,
+    },
+    Function {
+        id: "map",
+        r#fn: functional::map,
+    },
+    Function {
+        id: "filter",
+        r#fn: functional::filter,
+    },
+    Function {
+        id: "reduce",
+        r#fn: functional::reduce,
+    },
+    Function {
+        id: "range",
+        r#fn: functional::range,
+    },
+    Function {
+        id: "any",
+        r#fn: functional::any,
+    },
+    Function {
+        id: "all",
+        r#fn: functional::all,
+    },
+    Function {
+        id: "zip",
+        r#fn: functional::zip,
+    },
+    Function {
+        id: "enumerate",
+        r#fn: functional::enumerate,
+    },
+    Function {
+        id: "min",
+        r#fn: math::min,
+    },
+    Function {
+        id: "max",
+        r#fn: math::max,
+    },
+    Function {
+        id: "pow",
+        r#fn: math::pow,
+    },
+    Function {
+        id: "sqrt",
+        r#fn: math::sqrt,
+    },
+    Function {
+        id: "exp",
+        r#fn: math::exp,
+    },
+    Function {
+        id: "ln",
+        r#fn: math::ln,
+    },
+    Function {
+        id: "log10",
+        r#fn: math::log10,
+    },
+    Function {
+        id: "sin",
+        r#fn: math::sin,
+    },
+    Function {
+        id: "cos",
+        r#fn: math::cos,
+    },
+    Function {
+        id: "tan",
+        r#fn: math::tan,
+    },
+    Function {
+        id: "asin",
+        r#fn: math::asin,
+    },
+    Function {
+        id: "acos",
+        r#fn: math::acos,
+    },
+    Function {
+        id: "atan",
+        r#fn: math::atan,
+    },
+    Function {
+        id: "floor",
+        r#fn: math::floor,
+    },
+    Function {
+        id: "ceil",
+        r#fn: math::ceil,
+    },
+    Function {
+        id: "gcd",
+        r#fn: math::gcd,
+    },
+    Function {
+        id: "lcm",
+        r#fn: math::lcm,
+    },
+    Function {
+        id: "factorial",
+        r#fn: math::factorial,
+    },
+    Function {
+        id: "binomial",
+        r#fn: math::binomial,
+    },
+    Function {
+        id: "is_prime",
+        r#fn: math::is_prime,
+    },
+    Function {
+        id: "substr",
+        r#fn: string::substr,
+    },
+    Function {
+        id: "upper",
+        r#fn: string::upper,
+    },
+    Function {
+        id: "lower",
+        r#fn: string::lower,
+    },
+    Function {
+        id: "trim",
+        r#fn: string::trim,
+    },
+    Function {
+        id: "split",
+        r#fn: string::split,
+    },
+    Function {
+        id: "join",
+        r#fn: string::join,
+    },
+    Function {
+        id: "replace",
+        r#fn: string::replace,
+    },
+    Function {
+        id: "contains",
+        r#fn: string::contains,
+    },
+    Function {
+        id: "starts_with",
+        r#fn: string::starts_with,
+    },
+    Function {
+        id: "find",
+        r#fn: string::find,
+    },
+    Function {
+        id: "repeat",
+        r#fn: string::repeat,
+    },
 ];
 
 ///
 /// 組み込み関数を関数テーブルに設定する
 ///
-pub fn register(ft: &mut FunctionTable) -> () {
+pub fn register(ft: &mut FunctionTable) {
     for builtin in BUILTIN_LIST.clone() {
         ft.insert(
             builtin.id.to_string(),
@@ -39,23 +319,498 @@ pub fn register(ft: &mut FunctionTable) -> () {
     }
 }
 
+///
+/// 組み込みの定数の値を返す
+///
+pub fn constant(id: &str) -> Option<Value> { math::constant(id) }
+
 ///
 /// print
 ///
-fn print(params: Vec<Value>) -> Result<Value, String> {
-    match &params[0] {
-        Value::String(s) => {
+/// 第1引数が文字列の場合は、含まれる {} を第2引数以降で置き換えて出力する
+///
+fn print(_: &mut Context, params: Vec<Value>) -> Result<Value, String> {
+    println!("{}", format_print(&params)?);
+    Ok(Value::Unit)
+}
+
+///
+/// print で出力する文字列を返す
+///
+/// 第1引数が文字列の場合は、含まれる {} を第2引数以降で置き換える。それ以外は値を空白区切りで並べる
+///
+pub fn format_print(params: &[Value]) -> Result<String, String> {
+    match params.first() {
+        Some(Value::String(s)) => {
             let mut str: String = s.to_string();
 
             // 文字列中に含まれる{}を第2引数以降に変換する
             for v in &params[1..] {
                 str = str.replacen("{}", &v.to_string(), 1);
             }
+            Ok(str)
+        }
+        Some(_) => {
+            let strs: Vec<String> = params.iter().map(|v| v.to_string()).collect();
+            Ok(strs.join(" "))
+        }
+        None => Err("print 関数に引数が渡されていない".to_string()),
+    }
+}
+
+///
+/// int
+///
+/// 整数に変換する。浮動小数点数は 0 方向へ切り捨てる
+///
+fn int(_: &mut Context, params: Vec<Value>) -> Result<Value, String> {
+    match params.as_slice() {
+        [Value::Int(i)] => Ok(Value::Int(*i)),
+        [Value::BigInt(n)] => Ok(Value::BigInt(n.clone())),
+        [Value::Rational(r)] => Ok(Value::from_bigint(r.to_integer())),
+        [Value::Decimal(d)] => Ok(Value::from_bigint(BigInt::from(d.trunc().mantissa()))),
+        [Value::Float(f)] => match BigInt::from_f64(f.trunc()) {
+            Some(n) => Ok(Value::from_bigint(n)),
+            None => Err(format!("{} を整数に変換できない", f)),
+        },
+        [Value::Bool(b)] => Ok(Value::Int(*b as i32)),
+        [Value::String(s)] => match s.trim().parse() {
+            Ok(n) => Ok(Value::from_bigint(n)),
+            Err(_) => Err(format!("文字列 {:?} を整数に変換できない", s)),
+        },
+        [v] => Err(format!("{} を整数に変換できない", v)),
+        _ => Err(format!("int 関数は引数を 1 個受け取るが {} 個渡された", params.len())),
+    }
+}
+
+///
+/// float
+///
+/// 浮動小数点数に変換する。単位つきの量は単位を外した大きさを返す
+///
+fn float(_: &mut Context, params: Vec<Value>) -> Result<Value, String> {
+    match params.as_slice() {
+        [Value::Int(i)] => Ok(Value::Float(*i as f64)),
+        [Value::BigInt(n)] => Ok(Value::Float(n.to_f64().unwrap_or(f64::NAN))),
+        [Value::Rational(r)] => Ok(Value::Float(r.to_f64().unwrap_or(f64::NAN))),
+        [Value::Decimal(d)] => Ok(Value::Float(d.to_f64().unwrap_or(f64::NAN))),
+        [Value::Quantity(q)] => Ok(Value::Float(q.value)),
+        [Value::Float(f)] => Ok(Value::Float(*f)),
+        [Value::String(s)] => match s.trim().parse() {
+            Ok(f) => Ok(Value::Float(f)),
+            Err(_) => Err(format!("文字列 {:?} を浮動小数点数に変換できない", s)),
+        },
+        [v] => Err(format!("{} を浮動小数点数に変換できない", v)),
+        _ => Err(format!("float 関数は引数を 1 個受け取るが {} 個渡された", params.len())),
+    }
+}
+
+/// 整数、有理数、小数を有理数として取り出す
+fn to_rational(value: &Value) -> Option<BigRational> {
+    match value {
+        Value::Int(i) => Some(BigRational::from_integer(BigInt::from(*i))),
+        Value::BigInt(n) => Some(BigRational::from_integer(n.clone())),
+        Value::Rational(r) => Some(r.clone()),
+        Value::Decimal(d) => Some(BigRational::new(BigInt::from(d.mantissa()), BigInt::from(10).pow(d.scale()))),
+        _ => None,
+    }
+}
+
+///
+/// rational
+///
+/// 有理数を作成する。rational(x) は x を誤差なく有理数に変換し、rational(n, d) は n / d を返す
+///
+fn rational(_: &mut Context, params: Vec<Value>) -> Result<Value, String> {
+    match params.as_slice() {
+        [Value::Float(f)] => match BigRational::from_float(*f) {
+            Some(r) => Ok(Value::from_rational(r)),
+            None => Err(format!("{} を有理数に変換できない", f)),
+        },
+        [v] => match to_rational(v) {
+            Some(r) => Ok(Value::from_rational(r)),
+            None => Err(format!("{} を有理数に変換できない", v)),
+        },
+        [n, d] => match (to_rational(n), to_rational(d)) {
+            (Some(_), Some(d)) if d.is_zero() => Err(format!("{} / {} の0での割り算が発生した", n, d)),
+            (Some(n), Some(d)) => Ok(Value::from_rational(n / d)),
+            _ => Err(format!("{} / {} を有理数に変換できない", n, d)),
+        },
+        _ => Err(format!("rational 関数は引数を 1 個か 2 個受け取るが {} 個渡された", params.len())),
+    }
+}
+
+///
+/// fixed
+///
+/// 数値を小数点以下 digits 桁の 10 進数の文字列に変換する。桁に収まらない部分は設定 rounding の方法で丸める
+///
+/// 浮動小数点数も誤差なく有理数に変換してから丸めるため、どの型でも同じ値は同じ文字列になる。digits は 1000 桁までとする
+///
+fn fixed(context: &mut Context, params: Vec<Value>) -> Result<Value, String> {
+    const MAX_DIGITS: i32 = 1000;
+    let (value, digits) = match params.as_slice() {
+        [value, Value::Int(digits)] if (0..=MAX_DIGITS).contains(digits) => (value, *digits as usize),
+        [_, digits] => return Err(format!("fixed 関数の桁数には 0 以上 {} 以下の整数を指定するが {} が指定された", MAX_DIGITS, digits)),
+        _ => return Err(format!("fixed 関数は引数を 2 個受け取るが {} 個渡された", params.len())),
+    };
+    let r = match value {
+        Value::Float(f) => BigRational::from_float(*f),
+        _ => to_rational(value),
+    };
+    let r = match r {
+        Some(r) => r,
+        None => return Err(format!("{} を 10 進数に変換できない", value)),
+    };
+
+    // 10^digits 倍して整数に丸めた後、小数点を挿入する
+    let scaled = round_rational(&(r * BigRational::from_integer(BigInt::from(10).pow(digits as u32))), context.settings().rounding);
+    let mut text = scaled.abs().to_string();
+    if text.len() <= digits {
+        text = "0".repeat(digits + 1 - text.len()) + &text;
+    }
+    if digits > 0 {
+        text.insert(text.len() - digits, '.');
+    }
+    if scaled.is_negative() {
+        text.insert(0, '-');
+    }
+    Ok(Value::String(text))
+}
+
+/// 有理数を rounding の方法で整数に丸める
+fn round_rational(r: &BigRational, rounding: Rounding) -> BigInt {
+    match rounding {
+        Rounding::HalfUp => r.round().to_integer(),
+        Rounding::Truncate => r.trunc().to_integer(),
+        Rounding::HalfEven => {
+            // 切り捨てた値との差がちょうど 1/2 の場合だけ偶数の側を選ぶ
+            let floor = r.floor();
+            let twice = (r - &floor) * BigRational::from_integer(BigInt::from(2));
+            let floor = floor.to_integer();
+            match twice.cmp(&BigRational::from_integer(BigInt::from(1))) {
+                std::cmp::Ordering::Less => floor,
+                std::cmp::Ordering::Equal if floor.is_even() => floor,
+                _ => floor + 1,
+            }
+        }
+    }
+}
+
+/// 整数を接頭辞 prefix つきの radix 進数の文字列に変換する。負の数は '-' を先頭につける
+fn to_radix(id: &str, params: &[Value], radix: u32, prefix: &str) -> Result<Value, String> {
+    let n = match single(id, params)? {
+        Value::Int(i) => BigInt::from(*i),
+        Value::BigInt(n) => n.clone(),
+        v => return Err(format!("{} 関数には整数を指定するが {} が指定された", id, v)),
+    };
+    let sign = if n.is_negative() { "-" } else { "" };
+    Ok(Value::String(format!("{}{}{}", sign, prefix, n.abs().to_str_radix(radix))))
+}
+
+///
+/// hex
+///
+/// 整数を 16 進数の文字列（`0xff`）に変換する
+///
+fn hex(_: &mut Context, params: Vec<Value>) -> Result<Value, String> { to_radix("hex", &params, 16, "0x") }
+
+///
+/// bin
+///
+/// 整数を 2 進数の文字列（`0b1010`）に変換する
+///
+fn bin(_: &mut Context, params: Vec<Value>) -> Result<Value, String> { to_radix("bin", &params, 2, "0b") }
+
+///
+/// oct
+///
+/// 整数を 8 進数の文字列（`0o17`）に変換する
+///
+fn oct(_: &mut Context, params: Vec<Value>) -> Result<Value, String> { to_radix("oct", &params, 8, "0o") }
+
+///
+/// decimal
+///
+/// 小数に変換する。浮動小数点数は表示される桁数のままの小数にする
+///
+fn decimal(_: &mut Context, params: Vec<Value>) -> Result<Value, String> {
+    let result = match params.as_slice() {
+        [Value::Int(i)] => Some(Decimal::from(*i)),
+        [Value::BigInt(n)] => Decimal::from_str_exact(&n.to_string()).ok(),
+        [Value::Decimal(d)] => Some(*d),
+        [Value::Float(f)] => Decimal::from_f64(*f),
+        [Value::String(s)] => Decimal::from_str_exact(s.trim()).ok(),
+        [_] => None,
+        _ => return Err(format!("decimal 関数は引数を 1 個受け取るが {} 個渡された", params.len())),
+    };
+    match result {
+        Some(d) => Ok(Value::Decimal(d)),
+        None => Err(format!("{} を小数に変換できない", params[0])),
+    }
+}
+
+///
+/// scale
+///
+/// 小数の小数点以下の桁数を返す。整数は 0 桁とする
+///
+fn scale(_: &mut Context, params: Vec<Value>) -> Result<Value, String> {
+    match params.as_slice() {
+        [Value::Decimal(d)] => Ok(Value::Int(d.scale() as i32)),
+        [Value::Int(_)] | [Value::BigInt(_)] => Ok(Value::Int(0)),
+        [v] => Err(format!("{} の桁数は定義されていない", v)),
+        _ => Err(format!("scale 関数は引数を 1 個受け取るが {} 個渡された", params.len())),
+    }
+}
+
+/// 数値を複素数として取り出す
+fn to_complex(value: &Value) -> Option<Complex64> {
+    let re = match value {
+        Value::Complex(c) => return Some(*c),
+        Value::Int(i) => *i as f64,
+        Value::BigInt(n) => n.to_f64()?,
+        Value::Rational(r) => r.to_f64()?,
+        Value::Decimal(d) => d.to_f64()?,
+        Value::Float(f) => *f,
+        _ => return None,
+    };
+    Some(Complex64::new(re, 0.0))
+}
+
+/// 引数を 1 個だけ受け取る関数の引数を取り出す
+fn single<'a>(id: &str, params: &'a [Value]) -> Result<&'a Value, String> {
+    match params {
+        [value] => Ok(value),
+        _ => Err(format!("{} 関数は引数を 1 個受け取るが {} 個渡された", id, params.len())),
+    }
+}
+
+///
+/// re
+///
+/// 複素数の実部を返す。実数はそのまま返す
+///
+fn re(_: &mut Context, params: Vec<Value>) -> Result<Value, String> {
+    match single("re", &params)? {
+        Value::Complex(c) => Ok(Value::Float(c.re)),
+        v if to_complex(v).is_some() => Ok(v.clone()),
+        v => Err(format!("{} は数値ではない", v)),
+    }
+}
+
+///
+/// im
+///
+/// 複素数の虚部を返す。実数の虚部は 0 とする
+///
+fn im(_: &mut Context, params: Vec<Value>) -> Result<Value, String> {
+    match single("im", &params)? {
+        Value::Complex(c) => Ok(Value::Float(c.im)),
+        v if to_complex(v).is_some() => Ok(Value::Int(0)),
+        v => Err(format!("{} は数値ではない", v)),
+    }
+}
+
+///
+/// arg
+///
+/// 複素数の偏角を -π より大きく π 以下の範囲で返す
+///
+fn arg(_: &mut Context, params: Vec<Value>) -> Result<Value, String> {
+    let value = single("arg", &params)?;
+    match to_complex(value) {
+        Some(c) => Ok(Value::Float(c.arg())),
+        None => Err(format!("{} は数値ではない", value)),
+    }
+}
+
+///
+/// conj
+///
+/// 共役複素数を返す。実数はそのまま返す
+///
+fn conj(_: &mut Context, params: Vec<Value>) -> Result<Value, String> {
+    match single("conj", &params)? {
+        Value::Complex(c) => Ok(Value::Complex(c.conj())),
+        v if to_complex(v).is_some() => Ok(v.clone()),
+        v => Err(format!("{} は数値ではない", v)),
+    }
+}
+
+///
+/// polar
+///
+/// polar(r, theta) で大きさ r、偏角 theta の複素数を返す
+///
+fn polar(_: &mut Context, params: Vec<Value>) -> Result<Value, String> {
+    match params.as_slice() {
+        [r, theta] => match (to_complex(r), to_complex(theta)) {
+            (Some(r), Some(theta)) if r.im == 0.0 && theta.im == 0.0 => Ok(Value::Complex(Complex64::from_polar(r.re, theta.re))),
+            _ => Err(format!("polar 関数には実数を指定するが {} と {} が指定された", r, theta)),
+        },
+        _ => Err(format!("polar 関数は引数を 2 個受け取るが {} 個渡された", params.len())),
+    }
+}
+
+///
+/// to
+///
+/// to(value, "km/h") で単位つきの量を次元の等しい別の単位に換算する
+///
+fn to(_: &mut Context, params: Vec<Value>) -> Result<Value, String> {
+    match params.as_slice() {
+        [value, Value::String(name)] => {
+            let unit = match unit::parse(name) {
+                Some(unit) => unit,
+                None => return Err(format!("単位 {:?} は存在しない", name)),
+            };
+            match value {
+                Value::Quantity(q) => match q.convert(&unit) {
+                    Some(q) => Ok(Value::Quantity(q)),
+                    None => Err(format!("{} は {} と次元が異なるため換算できない", q, unit)),
+                },
+                v => Err(format!("{} は単位つきの量ではない", v)),
+            }
+        }
+        [_, unit] => Err(format!("to 関数の単位には文字列を指定するが {} が指定された", unit)),
+        _ => Err(format!("to 関数は引数を 2 個受け取るが {} 個渡された", params.len())),
+    }
+}
+
+///
+/// len
+///
+/// リストや辞書の要素数、もしくは文字列の文字数を返す
+///
+fn len(_: &mut Context, params: Vec<Value>) -> Result<Value, String> {
+    let n = match single("len", &params)? {
+        Value::List(l) => l.borrow().len(),
+        Value::Map(m) => m.borrow().len(),
+        Value::String(s) => s.chars().count(),
+        v => return Err(format!("{} の長さは定義されていない", v)),
+    };
+    Ok(Value::from_bigint(BigInt::from(n)))
+}
 
-            // 出力する
-            print!("{:?}\n", str);
+///
+/// push
+///
+/// push(xs, v) でリストの末尾に v を追加する
+///
+fn push(_: &mut Context, params: Vec<Value>) -> Result<Value, String> {
+    match params.as_slice() {
+        [Value::List(l), value] => {
+            l.borrow_mut().push(value.clone());
             Ok(Value::Unit)
         }
-        _ => Err(format!("print 関数にテキスト以外が渡された")),
+        [v, _] => Err(format!("{} はリストではない", v)),
+        _ => Err(format!("push 関数は引数を 2 個受け取るが {} 個渡された", params.len())),
+    }
+}
+
+///
+/// pop
+///
+/// リストの末尾の要素を取り除いて返す
+///
+fn pop(_: &mut Context, params: Vec<Value>) -> Result<Value, String> {
+    match single("pop", &params)? {
+        Value::List(l) => match l.borrow_mut().pop() {
+            Some(value) => Ok(value),
+            None => Err("空のリストから要素を取り出そうとした".to_string()),
+        },
+        v => Err(format!("{} はリストではない", v)),
+    }
+}
+
+///
+/// reverse
+///
+/// 要素を逆順に並べた新しいリストを返す
+///
+fn reverse(_: &mut Context, params: Vec<Value>) -> Result<Value, String> {
+    match single("reverse", &params)? {
+        Value::List(l) => Ok(Value::list(l.borrow().iter().rev().cloned().collect())),
+        v => Err(format!("{} はリストではない", v)),
+    }
+}
+
+///
+/// sort
+///
+/// 要素を昇順に並べた新しいリストを返す。比較できない要素が含まれる場合はエラーになる
+///
+fn sort(_: &mut Context, params: Vec<Value>) -> Result<Value, String> {
+    let mut items = match single("sort", &params)? {
+        Value::List(l) => l.borrow().clone(),
+        v => return Err(format!("{} はリストではない", v)),
+    };
+
+    // 並べ替える前に、すべての要素どうしが比較できることを確かめる
+    // 小数は有理数や浮動小数点数と比較できないため、小数があればそれとも比較する
+    let decimal = items.iter().find(|item| matches!(item, Value::Decimal(_)));
+    for pivot in items.first().into_iter().chain(decimal) {
+        for item in &items {
+            operator::total_order(pivot, item)?;
+        }
+    }
+
+    items.sort_by(|a, b| operator::total_order(a, b).unwrap());
+    Ok(Value::list(items))
+}
+
+///
+/// keys
+///
+/// 辞書のキーをリストで返す
+///
+fn keys(_: &mut Context, params: Vec<Value>) -> Result<Value, String> {
+    match single("keys", &params)? {
+        Value::Map(m) => Ok(Value::list(m.borrow().keys().map(|k| Value::String(k.clone())).collect())),
+        v => Err(format!("{} は辞書ではない", v)),
+    }
+}
+
+///
+/// values
+///
+/// 辞書の値をキーの順にリストで返す
+///
+fn values(_: &mut Context, params: Vec<Value>) -> Result<Value, String> {
+    match single("values", &params)? {
+        Value::Map(m) => Ok(Value::list(m.borrow().values().cloned().collect())),
+        v => Err(format!("{} は辞書ではない", v)),
+    }
+}
+
+///
+/// has
+///
+/// has(m, key) で辞書にキーが存在するかどうかを返す
+///
+fn has(_: &mut Context, params: Vec<Value>) -> Result<Value, String> {
+    match params.as_slice() {
+        [Value::Map(m), Value::String(key)] => Ok(Value::Bool(m.borrow().contains_key(key))),
+        [Value::Map(_), key] => Err(format!("辞書のキーには文字列を指定するが {} が指定された", key)),
+        [v, _] => Err(format!("{} は辞書ではない", v)),
+        _ => Err(format!("has 関数は引数を 2 個受け取るが {} 個渡された", params.len())),
+    }
+}
+
+///
+/// remove
+///
+/// remove(m, key) で辞書からキーを取り除き、その値を返す
+///
+fn remove(_: &mut Context, params: Vec<Value>) -> Result<Value, String> {
+    match params.as_slice() {
+        [Value::Map(m), Value::String(key)] => match m.borrow_mut().remove(key) {
+            Some(value) => Ok(value),
+            None => Err(format!("キー {:?} が存在しない", key)),
+        },
+        [Value::Map(_), key] => Err(format!("辞書のキーには文字列を指定するが {} が指定された", key)),
+        [v, _] => Err(format!("{} は辞書ではない", v)),
+        _ => Err(format!("remove 関数は引数を 2 個受け取るが {} 個渡された", params.len())),
     }
 }
@@ -0,0 +1,167 @@
+use num_bigint::BigInt;
+
+use super::single;
+use crate::enums::AsBool;
+use crate::enums::Value;
+use crate::evaluator::Context;
+
+/// リストの要素を取り出す
+fn items(id: &str, value: &Value) -> Result<Vec<Value>, String> {
+    match value {
+        Value::List(l) => Ok(l.borrow().clone()),
+        v => Err(format!("{} 関数にはリストを渡すが {} が渡された", id, v)),
+    }
+}
+
+///
+/// map
+///
+/// map(xs, f) で各要素に f を適用した結果のリストを返す
+///
+pub(super) fn map(ctx: &mut Context, params: Vec<Value>) -> Result<Value, String> {
+    match params.as_slice() {
+        [xs, f] => {
+            let mut result = Vec::new();
+            for x in items("map", xs)? {
+                result.push(ctx.call(f, vec![x])?);
+            }
+            Ok(Value::list(result))
+        }
+        _ => Err(format!("map 関数は引数を 2 個受け取るが {} 個渡された", params.len())),
+    }
+}
+
+///
+/// filter
+///
+/// filter(xs, f) で f が真を返した要素だけのリストを返す
+///
+pub(super) fn filter(ctx: &mut Context, params: Vec<Value>) -> Result<Value, String> {
+    match params.as_slice() {
+        [xs, f] => {
+            let mut result = Vec::new();
+            for x in items("filter", xs)? {
+                if ctx.call(f, vec![x.clone()])?.as_bool() {
+                    result.push(x);
+                }
+            }
+            Ok(Value::list(result))
+        }
+        _ => Err(format!("filter 関数は引数を 2 個受け取るが {} 個渡された", params.len())),
+    }
+}
+
+///
+/// reduce
+///
+/// reduce(xs, init, f) で init から始めて f(累積値, 要素) を順に適用した結果を返す
+///
+pub(super) fn reduce(ctx: &mut Context, params: Vec<Value>) -> Result<Value, String> {
+    match params.as_slice() {
+        [xs, init, f] => {
+            let mut acc = init.clone();
+            for x in items("reduce", xs)? {
+                acc = ctx.call(f, vec![acc, x])?;
+            }
+            Ok(acc)
+        }
+        _ => Err(format!("reduce 関数は引数を 3 個受け取るが {} 個渡された", params.len())),
+    }
+}
+
+///
+/// range
+///
+/// range(b), range(a, b), range(a, b, step) で a 以上 b 未満の整数を step ごとに並べたリストを返す
+///
+/// step が負の場合は a から b より大きい間だけ減らしていく。要素数は MAX_RANGE_LEN までとする
+///
+pub(super) fn range(_: &mut Context, params: Vec<Value>) -> Result<Value, String> {
+    let (from, to, step) = match params.as_slice() {
+        [Value::Int(b)] => (0, *b, 1),
+        [Value::Int(a), Value::Int(b)] => (*a, *b, 1),
+        [Value::Int(a), Value::Int(b), Value::Int(step)] => (*a, *b, *step),
+        [] | [_, _, _, _, ..] => return Err(format!("range 関数は引数を 1 個から 3 個受け取るが {} 個渡された", params.len())),
+        _ => return Err("range 関数には整数を指定する".to_string()),
+    };
+    if step == 0 {
+        return Err("range 関数の step に 0 は指定できない".to_string());
+    }
+
+    // i32 の範囲を超えないよう i64 で数える
+    const MAX_RANGE_LEN: i64 = 1_000_000;
+    let len = (to as i64 - from as i64 + step as i64 - step.signum() as i64) / step as i64;
+    if len > MAX_RANGE_LEN {
+        return Err(format!("range 関数で作れるリストの長さは {} までだが {} 個の要素が指定された", MAX_RANGE_LEN, len));
+    }
+    let mut result = Vec::new();
+    let mut i = from as i64;
+    while (step > 0 && i < to as i64) || (step < 0 && i > to as i64) {
+        result.push(Value::Int(i as i32));
+        i += step as i64;
+    }
+    Ok(Value::list(result))
+}
+
+///
+/// any と all の述語を先頭の要素から順に適用し、結果が wanted になる要素があれば true を返す
+///
+/// 見つかった時点で残りの要素には適用しない。述語を省略した場合は要素そのものを真偽値として扱う
+///
+fn test(ctx: &mut Context, id: &str, params: &[Value], wanted: bool) -> Result<bool, String> {
+    let (xs, f) = match params {
+        [xs] => (xs, None),
+        [xs, f] => (xs, Some(f)),
+        _ => return Err(format!("{} 関数は引数を 1 個か 2 個受け取るが {} 個渡された", id, params.len())),
+    };
+    for x in items(id, xs)? {
+        let value = match f {
+            Some(f) => ctx.call(f, vec![x])?,
+            None => x,
+        };
+        if value.as_bool() == wanted {
+            return Ok(true);
+        }
+    }
+    Ok(false)
+}
+
+///
+/// any
+///
+/// any(xs, f) で f が真を返す要素が 1 つでもあれば true を返す。f は省略できる
+///
+pub(super) fn any(ctx: &mut Context, params: Vec<Value>) -> Result<Value, String> { Ok(Value::Bool(test(ctx, "any", &params, true)?)) }
+
+///
+/// all
+///
+/// all(xs, f) で f がすべての要素に対して真を返せば true を返す。f は省略できる
+///
+pub(super) fn all(ctx: &mut Context, params: Vec<Value>) -> Result<Value, String> { Ok(Value::Bool(!test(ctx, "all", &params, false)?)) }
+
+///
+/// zip
+///
+/// zip(xs, ys) で同じ位置の要素の組 [x, y] のリストを返す。長さは短い方に合わせる
+///
+pub(super) fn zip(_: &mut Context, params: Vec<Value>) -> Result<Value, String> {
+    match params.as_slice() {
+        [xs, ys] => {
+            let pairs = items("zip", xs)?.into_iter().zip(items("zip", ys)?).map(|(x, y)| Value::list(vec![x, y]));
+            Ok(Value::list(pairs.collect()))
+        }
+        _ => Err(format!("zip 関数は引数を 2 個受け取るが {} 個渡された", params.len())),
+    }
+}
+
+///
+/// enumerate
+///
+/// 要素と位置の組 [i, x] のリストを返す
+///
+pub(super) fn enumerate(_: &mut Context, params: Vec<Value>) -> Result<Value, String> {
+    let xs = items("enumerate", single("enumerate", &params)?)?;
+    let pairs = xs.into_iter().enumerate().map(|(i, x)| Value::list(vec![Value::from_bigint(BigInt::from(i)), x]));
+    Ok(Value::list(pairs.collect()))
+}
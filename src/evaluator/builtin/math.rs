@@ -0,0 +1,462 @@
+use std::cmp::Ordering;
+
+use num_bigint::BigInt;
+use num_complex::Complex64;
+use num_integer::Integer;
+use num_traits::FromPrimitive;
+use num_traits::One;
+use num_traits::Signed;
+use num_traits::ToPrimitive;
+use num_traits::Zero;
+
+use super::operator;
+use super::single;
+use crate::enums::BinOp;
+use crate::enums::Rounding;
+use crate::enums::Value;
+use crate::evaluator::Context;
+use crate::unit::Quantity;
+
+// 数学定数の一覧
+static CONSTANT_LIST: [(&str, f64); 2] = [("pi", std::f64::consts::PI), ("e", std::f64::consts::E)];
+
+///
+/// 数学定数の値を返す
+///
+/// 同じ名前の変数が定義されている場合はそちらが優先される
+///
+pub(super) fn constant(id: &str) -> Option<Value> { CONSTANT_LIST.iter().find(|(name, _)| *name == id).map(|(_, value)| Value::Float(*value)) }
+
+/// 実数を浮動小数点数として取り出す
+fn real(id: &str, value: &Value) -> Result<f64, String> {
+    let result = match value {
+        Value::Int(i) => Some(*i as f64),
+        Value::BigInt(n) => n.to_f64(),
+        Value::Rational(r) => r.to_f64(),
+        Value::Decimal(d) => d.to_f64(),
+        Value::Float(f) => Some(*f),
+        _ => None,
+    };
+    result.ok_or_else(|| format!("{} 関数には実数を指定するが {} が指定された", id, value))
+}
+
+/// 整数を多倍長整数として取り出す
+fn integer(id: &str, value: &Value) -> Result<BigInt, String> {
+    match value {
+        Value::Int(i) => Ok(BigInt::from(*i)),
+        Value::BigInt(n) => Ok(n.clone()),
+        v => Err(format!("{} 関数には整数を指定するが {} が指定された", id, v)),
+    }
+}
+
+/// 0 以上の整数を取り出す
+fn natural(id: &str, value: &Value) -> Result<BigInt, String> {
+    match integer(id, value)? {
+        n if n.is_negative() => Err(format!("{} 関数には 0 以上の整数を指定するが {} が指定された", id, n)),
+        n => Ok(n),
+    }
+}
+
+/// 引数を 2 個受け取る関数の引数を取り出す
+fn pair<'a>(id: &str, params: &'a [Value]) -> Result<(&'a Value, &'a Value), String> {
+    match params {
+        [a, b] => Ok((a, b)),
+        _ => Err(format!("{} 関数は引数を 2 個受け取るが {} 個渡された", id, params.len())),
+    }
+}
+
+///
+/// 実数と複素数の両方を受け取る関数を適用する
+///
+/// 複素数には g を、それ以外の実数には f を適用する
+///
+fn apply(id: &str, params: &[Value], f: fn(f64) -> f64, g: fn(Complex64) -> Complex64) -> Result<Value, String> {
+    match single(id, params)? {
+        Value::Complex(c) => Ok(Value::Complex(g(*c))),
+        v => Ok(Value::Float(f(real(id, v)?))),
+    }
+}
+
+/// 実数だけを受け取る関数を適用する。定義域の外の値はエラーになる
+fn apply_real(id: &str, params: &[Value], domain: fn(f64) -> bool, f: fn(f64) -> f64) -> Result<Value, String> {
+    let x = real(id, single(id, params)?)?;
+    if !domain(x) {
+        return Err(format!("{} 関数は {} に対して定義されていない", id, x));
+    }
+    Ok(Value::Float(f(x)))
+}
+
+///
+/// abs
+///
+/// 絶対値を返す。複素数の場合は大きさを浮動小数点数で返す
+///
+/// 単位つきの量は単位を保ったまま返す
+///
+pub(super) fn abs(_: &mut Context, params: Vec<Value>) -> Result<Value, String> {
+    match single("abs", &params)? {
+        Value::Int(i) => Ok(Value::from_bigint(BigInt::from(*i).abs())),
+        Value::BigInt(n) => Ok(Value::BigInt(n.abs())),
+        Value::Rational(r) => Ok(Value::Rational(r.abs())),
+        Value::Decimal(d) => Ok(Value::Decimal(d.abs())),
+        Value::Float(f) => Ok(Value::Float(f.abs())),
+        Value::Complex(c) => Ok(Value::Float(c.norm())),
+        Value::Quantity(q) => Ok(Value::Quantity(Quantity {
+            value: q.value.abs(),
+            unit: q.unit.clone(),
+        })),
+        v => Err(format!("{} は数値ではない", v)),
+    }
+}
+
+///
+/// round
+///
+/// round(x, digits, mode) で x を小数点以下 digits 桁に丸める
+///
+/// digits を省略すると 0 桁、mode を省略すると "half_even" で丸める。digits は小数と同じく 28 桁までとする
+///
+pub(super) fn round(_: &mut Context, params: Vec<Value>) -> Result<Value, String> {
+    const MAX_DIGITS: i32 = 28;
+    let (value, digits, mode) = match params.as_slice() {
+        [value] => (value, 0, "half_even"),
+        [value, Value::Int(digits)] if (0..=MAX_DIGITS).contains(digits) => (value, *digits as u32, "half_even"),
+        [value, Value::Int(digits), Value::String(mode)] if (0..=MAX_DIGITS).contains(digits) => (value, *digits as u32, mode.as_str()),
+        [_, digits] | [_, digits, Value::String(_)] => return Err(format!("round 関数の桁数には 0 以上 {} 以下の整数を指定するが {} が指定された", MAX_DIGITS, digits)),
+        [_, _, mode] => return Err(format!("round 関数の丸め方には文字列を指定するが {} が指定された", mode)),
+        _ => return Err(format!("round 関数は引数を 1 個から 3 個受け取るが {} 個渡された", params.len())),
+    };
+    let rounding = match Rounding::parse(mode) {
+        Some(rounding) => rounding,
+        None => return Err(format!("丸め方 {:?} は存在しない", mode)),
+    };
+
+    match value {
+        Value::Int(_) | Value::BigInt(_) => Ok(value.clone()),
+        Value::Decimal(d) => {
+            let mut d = d.round_dp_with_strategy(digits, rounding.strategy());
+            d.rescale(digits);
+            Ok(Value::Decimal(d))
+        }
+        Value::Float(f) => {
+            let factor = 10f64.powi(digits as i32);
+            let scaled = f * factor;
+            // 桁数が大きく小数部を持たなくなる場合は丸める桁がないため、そのまま返す
+            if !scaled.is_finite() || scaled.abs() >= 2f64.powi(f64::MANTISSA_DIGITS as i32) {
+                return Ok(Value::Float(*f));
+            }
+            let rounded = match rounding {
+                Rounding::HalfEven => scaled.round_ties_even(),
+                Rounding::HalfUp => scaled.round(),
+                Rounding::Truncate => scaled.trunc(),
+            };
+            Ok(Value::Float(rounded / factor))
+        }
+        v => Err(format!("{} を丸めることはできない", v)),
+    }
+}
+
+/// 最小値もしくは最大値を求める。引数がリスト 1 個だけの場合はその要素から求める
+fn extreme(id: &str, params: &[Value], wanted: Ordering) -> Result<Value, String> {
+    let items = match params {
+        [Value::List(l)] => l.borrow().clone(),
+        _ => params.to_vec(),
+    };
+    let mut items = items.into_iter();
+    let mut result = match items.next() {
+        Some(first) => first,
+        None => return Err(format!("{} 関数には値を 1 個以上渡す", id)),
+    };
+    for item in items {
+        if operator::order(&item, &result)? == wanted {
+            result = item;
+        }
+    }
+    Ok(result)
+}
+
+///
+/// min
+///
+/// min(a, b, ...) で最小の値を返す。min(xs) でリストの要素の最小値を返す
+///
+pub(super) fn min(_: &mut Context, params: Vec<Value>) -> Result<Value, String> { extreme("min", &params, Ordering::Less) }
+
+///
+/// max
+///
+/// max(a, b, ...) で最大の値を返す。max(xs) でリストの要素の最大値を返す
+///
+pub(super) fn max(_: &mut Context, params: Vec<Value>) -> Result<Value, String> { extreme("max", &params, Ordering::Greater) }
+
+///
+/// pow
+///
+/// pow(x, y) で x の y 乗を返す。x ** y と同じく計算し、小数の桁数や負の指数の扱いも同じになる
+///
+/// 実数にならない場合はエラーになる
+///
+pub(super) fn pow(context: &mut Context, params: Vec<Value>) -> Result<Value, String> {
+    let (base, exponent) = pair("pow", &params)?;
+    match operator::binary(&BinOp::Pow, base.clone(), exponent.clone(), context.settings())? {
+        Value::Float(result) if result.is_nan() => Err(format!("{} の {} 乗は実数にならない", base, exponent)),
+        result => Ok(result),
+    }
+}
+
+///
+/// sqrt
+///
+/// 平方根を返す。整数の平方数は整数で返す
+///
+/// 負の実数はエラーになる。複素数の平方根は複素数で返す
+///
+pub(super) fn sqrt(_: &mut Context, params: Vec<Value>) -> Result<Value, String> {
+    match single("sqrt", &params)? {
+        Value::Complex(c) => Ok(Value::Complex(c.sqrt())),
+        v @ (Value::Int(_) | Value::BigInt(_)) => {
+            let n = integer("sqrt", v)?;
+            if n.is_negative() {
+                return Err(format!("負の数 {} の平方根は実数にならない", n));
+            }
+            let root = n.sqrt();
+            if &root * &root == n {
+                Ok(Value::from_bigint(root))
+            } else {
+                Ok(Value::Float(n.to_f64().unwrap_or(f64::INFINITY).sqrt()))
+            }
+        }
+        v => match real("sqrt", v)? {
+            x if x < 0.0 => Err(format!("負の数 {} の平方根は実数にならない", x)),
+            x => Ok(Value::Float(x.sqrt())),
+        },
+    }
+}
+
+///
+/// exp
+///
+/// e の x 乗を返す
+///
+pub(super) fn exp(_: &mut Context, params: Vec<Value>) -> Result<Value, String> { apply("exp", &params, f64::exp, Complex64::exp) }
+
+///
+/// ln
+///
+/// 自然対数を返す。0 以下の実数はエラーになる
+///
+pub(super) fn ln(_: &mut Context, params: Vec<Value>) -> Result<Value, String> {
+    match single("ln", &params)? {
+        Value::Complex(c) if c.is_zero() => Err("ln 関数は 0 に対して定義されていない".to_string()),
+        Value::Complex(c) => Ok(Value::Complex(c.ln())),
+        _ => apply_real("ln", &params, |x| x > 0.0, f64::ln),
+    }
+}
+
+///
+/// log10
+///
+/// 常用対数を返す。0 以下の実数はエラーになる
+///
+pub(super) fn log10(_: &mut Context, params: Vec<Value>) -> Result<Value, String> { apply_real("log10", &params, |x| x > 0.0, f64::log10) }
+
+///
+/// sin
+///
+/// 正弦を返す。角度はラジアンで指定する
+///
+pub(super) fn sin(_: &mut Context, params: Vec<Value>) -> Result<Value, String> { apply("sin", &params, f64::sin, Complex64::sin) }
+
+///
+/// cos
+///
+/// 余弦を返す。角度はラジアンで指定する
+///
+pub(super) fn cos(_: &mut Context, params: Vec<Value>) -> Result<Value, String> { apply("cos", &params, f64::cos, Complex64::cos) }
+
+///
+/// tan
+///
+/// 正接を返す。角度はラジアンで指定する
+///
+pub(super) fn tan(_: &mut Context, params: Vec<Value>) -> Result<Value, String> { apply("tan", &params, f64::tan, Complex64::tan) }
+
+///
+/// asin
+///
+/// 逆正弦を -π/2 以上 π/2 以下の範囲で返す。-1 以上 1 以下の実数だけを受け取る
+///
+pub(super) fn asin(_: &mut Context, params: Vec<Value>) -> Result<Value, String> { apply_real("asin", &params, |x| (-1.0..=1.0).contains(&x), f64::asin) }
+
+///
+/// acos
+///
+/// 逆余弦を 0 以上 π 以下の範囲で返す。-1 以上 1 以下の実数だけを受け取る
+///
+pub(super) fn acos(_: &mut Context, params: Vec<Value>) -> Result<Value, String> { apply_real("acos", &params, |x| (-1.0..=1.0).contains(&x), f64::acos) }
+
+///
+/// atan
+///
+/// 逆正接を -π/2 より大きく π/2 より小さい範囲で返す
+///
+pub(super) fn atan(_: &mut Context, params: Vec<Value>) -> Result<Value, String> { apply_real("atan", &params, |x| !x.is_nan(), f64::atan) }
+
+/// 実数を整数に丸める。up が真なら正の無限大方向、偽なら負の無限大方向へ丸める
+fn to_integer(id: &str, params: &[Value], up: bool) -> Result<Value, String> {
+    let result = match single(id, params)? {
+        Value::Int(_) | Value::BigInt(_) => return Ok(params[0].clone()),
+        Value::Rational(r) => Some(if up { r.ceil() } else { r.floor() }.to_integer()),
+        Value::Decimal(d) => Some(BigInt::from(if up { d.ceil() } else { d.floor() }.mantissa())),
+        Value::Float(f) => BigInt::from_f64(if up { f.ceil() } else { f.floor() }),
+        v => return Err(format!("{} 関数には実数を指定するが {} が指定された", id, v)),
+    };
+    match result {
+        Some(n) => Ok(Value::from_bigint(n)),
+        None => Err(format!("{} を整数に変換できない", params[0])),
+    }
+}
+
+///
+/// floor
+///
+/// x 以下の最大の整数を返す
+///
+pub(super) fn floor(_: &mut Context, params: Vec<Value>) -> Result<Value, String> { to_integer("floor", &params, false) }
+
+///
+/// ceil
+///
+/// x 以上の最小の整数を返す
+///
+pub(super) fn ceil(_: &mut Context, params: Vec<Value>) -> Result<Value, String> { to_integer("ceil", &params, true) }
+
+///
+/// gcd
+///
+/// 最大公約数を返す。結果は 0 以上になる
+///
+pub(super) fn gcd(_: &mut Context, params: Vec<Value>) -> Result<Value, String> {
+    let (a, b) = pair("gcd", &params)?;
+    Ok(Value::from_bigint(integer("gcd", a)?.gcd(&integer("gcd", b)?)))
+}
+
+///
+/// lcm
+///
+/// 最小公倍数を返す。結果は 0 以上になる
+///
+pub(super) fn lcm(_: &mut Context, params: Vec<Value>) -> Result<Value, String> {
+    let (a, b) = pair("lcm", &params)?;
+    Ok(Value::from_bigint(integer("lcm", a)?.lcm(&integer("lcm", b)?)))
+}
+
+/// factorial と binomial で掛け合わせる項の数の上限
+const MAX_TERMS: u32 = 10000;
+
+///
+/// factorial
+///
+/// n の階乗を返す。0 の階乗は 1 とする
+///
+/// 計算に時間がかかりすぎないように n は MAX_TERMS 以下に制限する
+///
+pub(super) fn factorial(_: &mut Context, params: Vec<Value>) -> Result<Value, String> {
+    let n = natural("factorial", single("factorial", &params)?)?;
+    if n > BigInt::from(MAX_TERMS) {
+        return Err(format!("factorial 関数は {} 以下の整数だけを計算できるが {} が指定された", MAX_TERMS, n));
+    }
+    let mut result = BigInt::one();
+    let mut i = BigInt::one();
+    while i <= n {
+        result *= &i;
+        i += 1;
+    }
+    Ok(Value::from_bigint(result))
+}
+
+///
+/// binomial
+///
+/// binomial(n, k) で n 個から k 個を選ぶ組み合わせの数を返す。k が n より大きい場合は 0 を返す
+///
+/// factorial と同じく、k と n - k の小さい方は MAX_TERMS 以下に制限する
+///
+pub(super) fn binomial(_: &mut Context, params: Vec<Value>) -> Result<Value, String> {
+    let (n, k) = pair("binomial", &params)?;
+    let (n, k) = (natural("binomial", n)?, natural("binomial", k)?);
+    if k > n {
+        return Ok(Value::Int(0));
+    }
+
+    // 小さい方の k で計算する。途中の値は常に組み合わせの数なので割り切れる
+    let k = std::cmp::min(k.clone(), &n - &k);
+    if k > BigInt::from(MAX_TERMS) {
+        return Err(format!("binomial 関数は k と n - k の小さい方が {} 以下の場合だけ計算できるが {} が指定された", MAX_TERMS, k));
+    }
+    let mut result = BigInt::one();
+    let mut i = BigInt::zero();
+    while i < k {
+        result = result * (&n - &i) / (&i + 1);
+        i += 1;
+    }
+    Ok(Value::from_bigint(result))
+}
+
+/// a * b mod m をあふれないように計算する
+fn mul_mod(a: u64, b: u64, m: u64) -> u64 { (a as u128 * b as u128 % m as u128) as u64 }
+
+/// a の e 乗 mod m を計算する
+fn pow_mod(mut a: u64, mut e: u64, m: u64) -> u64 {
+    let mut result = 1;
+    a %= m;
+    while e > 0 {
+        if e & 1 == 1 {
+            result = mul_mod(result, a, m);
+        }
+        a = mul_mod(a, a, m);
+        e >>= 1;
+    }
+    result
+}
+
+///
+/// is_prime
+///
+/// 素数かどうかを返す。2^64 未満の整数を判定できる
+///
+/// 最初の 12 個の素数を底とするミラー・ラビン判定は 2^64 未満で誤判定しない
+///
+pub(super) fn is_prime(_: &mut Context, params: Vec<Value>) -> Result<Value, String> {
+    let n = integer("is_prime", single("is_prime", &params)?)?;
+    if n < BigInt::from(2) {
+        return Ok(Value::Bool(false));
+    }
+    let n = match n.to_u64() {
+        Some(n) => n,
+        None => return Err(format!("is_prime 関数は 2^64 未満の整数だけを判定できるが {} が指定された", n)),
+    };
+
+    const BASES: [u64; 12] = [2, 3, 5, 7, 11, 13, 17, 19, 23, 29, 31, 37];
+    if let Some(p) = BASES.iter().find(|p| n % **p == 0) {
+        return Ok(Value::Bool(n == *p));
+    }
+
+    // n - 1 = d * 2^s と分解する
+    let s = (n - 1).trailing_zeros();
+    let d = (n - 1) >> s;
+    let composite = BASES.iter().any(|a| {
+        let mut x = pow_mod(*a, d, n);
+        if x == 1 || x == n - 1 {
+            return false;
+        }
+        for _ in 1..s {
+            x = mul_mod(x, x, n);
+            if x == n - 1 {
+                return false;
+            }
+        }
+        true
+    });
+    Ok(Value::Bool(!composite))
+}
@@ -0,0 +1,171 @@
+use num_bigint::BigInt;
+
+use super::single;
+use crate::enums::Value;
+use crate::evaluator::Context;
+
+/// 文字列を取り出す
+fn text<'a>(id: &str, value: &'a Value) -> Result<&'a str, String> {
+    match value {
+        Value::String(s) => Ok(s),
+        v => Err(format!("{} 関数には文字列を指定するが {} が指定された", id, v)),
+    }
+}
+
+/// 0 以上の整数を位置や個数として取り出す
+fn count(id: &str, value: &Value) -> Result<usize, String> {
+    match value {
+        Value::Int(i) if *i >= 0 => Ok(*i as usize),
+        v => Err(format!("{} 関数には 0 以上の整数を指定するが {} が指定された", id, v)),
+    }
+}
+
+/// 2 個の文字列を受け取る関数の引数を取り出す
+fn texts<'a>(id: &str, params: &'a [Value]) -> Result<(&'a str, &'a str), String> {
+    match params {
+        [s, t] => Ok((text(id, s)?, text(id, t)?)),
+        _ => Err(format!("{} 関数は引数を 2 個受け取るが {} 個渡された", id, params.len())),
+    }
+}
+
+///
+/// substr
+///
+/// substr(s, start, n) で start 文字目から n 文字を取り出す。n を省略すると末尾まで取り出す
+///
+/// 位置は文字単位で数え、範囲が末尾を超える場合は末尾までになる
+///
+pub(super) fn substr(_: &mut Context, params: Vec<Value>) -> Result<Value, String> {
+    let (s, start, n) = match params.as_slice() {
+        [s, start] => (text("substr", s)?, count("substr", start)?, None),
+        [s, start, n] => (text("substr", s)?, count("substr", start)?, Some(count("substr", n)?)),
+        _ => return Err(format!("substr 関数は引数を 2 個か 3 個受け取るが {} 個渡された", params.len())),
+    };
+    let len = s.chars().count();
+    if start > len {
+        return Err(format!("インデックス {} は範囲外（長さ {}）", start, len));
+    }
+    let chars = s.chars().skip(start);
+    Ok(Value::String(match n {
+        Some(n) => chars.take(n).collect(),
+        None => chars.collect(),
+    }))
+}
+
+///
+/// upper
+///
+/// 英字を大文字にした文字列を返す
+///
+pub(super) fn upper(_: &mut Context, params: Vec<Value>) -> Result<Value, String> { Ok(Value::String(text("upper", single("upper", &params)?)?.to_uppercase())) }
+
+///
+/// lower
+///
+/// 英字を小文字にした文字列を返す
+///
+pub(super) fn lower(_: &mut Context, params: Vec<Value>) -> Result<Value, String> { Ok(Value::String(text("lower", single("lower", &params)?)?.to_lowercase())) }
+
+///
+/// trim
+///
+/// 先頭と末尾の空白を取り除いた文字列を返す
+///
+pub(super) fn trim(_: &mut Context, params: Vec<Value>) -> Result<Value, String> { Ok(Value::String(text("trim", single("trim", &params)?)?.trim().to_string())) }
+
+///
+/// split
+///
+/// split(s, sep) で s を sep で区切った文字列のリストを返す。sep が空文字列なら 1 文字ずつに分ける
+///
+pub(super) fn split(_: &mut Context, params: Vec<Value>) -> Result<Value, String> {
+    let (s, sep) = texts("split", &params)?;
+    let parts: Vec<Value> = if sep.is_empty() {
+        s.chars().map(|c| Value::String(c.to_string())).collect()
+    } else {
+        s.split(sep).map(|part| Value::String(part.to_string())).collect()
+    };
+    Ok(Value::list(parts))
+}
+
+///
+/// join
+///
+/// join(xs, sep) でリストの要素を sep で区切ってつなげた文字列を返す
+///
+/// 文字列以外の要素は表示される形の文字列にしてつなげる
+///
+pub(super) fn join(_: &mut Context, params: Vec<Value>) -> Result<Value, String> {
+    match params.as_slice() {
+        [Value::List(l), sep] => {
+            let sep = text("join", sep)?;
+            let parts: Vec<String> = l.borrow().iter().map(|v| v.to_string()).collect();
+            Ok(Value::String(parts.join(sep)))
+        }
+        [v, _] => Err(format!("{} はリストではない", v)),
+        _ => Err(format!("join 関数は引数を 2 個受け取るが {} 個渡された", params.len())),
+    }
+}
+
+///
+/// replace
+///
+/// replace(s, from, to) で s に含まれる from をすべて to に置き換えた文字列を返す
+///
+pub(super) fn replace(_: &mut Context, params: Vec<Value>) -> Result<Value, String> {
+    match params.as_slice() {
+        [s, from, to] => {
+            let (s, from, to) = (text("replace", s)?, text("replace", from)?, text("replace", to)?);
+            if from.is_empty() {
+                return Err("replace 関数で置き換える文字列に空文字列は指定できない".to_string());
+            }
+            Ok(Value::String(s.replace(from, to)))
+        }
+        _ => Err(format!("replace 関数は引数を 3 個受け取るが {} 個渡された", params.len())),
+    }
+}
+
+///
+/// contains
+///
+/// contains(s, t) で s が t を含むかどうかを返す
+///
+pub(super) fn contains(_: &mut Context, params: Vec<Value>) -> Result<Value, String> {
+    let (s, t) = texts("contains", &params)?;
+    Ok(Value::Bool(s.contains(t)))
+}
+
+///
+/// starts_with
+///
+/// starts_with(s, t) で s が t で始まるかどうかを返す
+///
+pub(super) fn starts_with(_: &mut Context, params: Vec<Value>) -> Result<Value, String> {
+    let (s, t) = texts("starts_with", &params)?;
+    Ok(Value::Bool(s.starts_with(t)))
+}
+
+///
+/// find
+///
+/// find(s, t) で s の中で最初に t が現れる位置を文字単位で返す。見つからなければ -1 を返す
+///
+pub(super) fn find(_: &mut Context, params: Vec<Value>) -> Result<Value, String> {
+    let (s, t) = texts("find", &params)?;
+    match s.find(t) {
+        Some(offset) => Ok(Value::from_bigint(BigInt::from(s[..offset].chars().count()))),
+        None => Ok(Value::Int(-1)),
+    }
+}
+
+///
+/// repeat
+///
+/// repeat(s, n) で s を n 回繰り返した文字列を返す
+///
+pub(super) fn repeat(_: &mut Context, params: Vec<Value>) -> Result<Value, String> {
+    match params.as_slice() {
+        [s, n] => Ok(Value::String(text("repeat", s)?.repeat(count("repeat", n)?))),
+        _ => Err(format!("repeat 関数は引数を 2 個受け取るが {} 個渡された", params.len())),
+    }
+}
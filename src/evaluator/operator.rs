@@ -0,0 +1,683 @@
+use std::cmp::Ordering;
+use std::rc::Rc;
+
+use num_bigint::BigInt;
+use num_integer::Integer;
+use num_complex::Complex64;
+use num_rational::BigRational;
+use num_traits::One;
+use num_traits::Signed;
+use num_traits::ToPrimitive;
+use num_traits::Zero;
+use rust_decimal::Decimal;
+
+use crate::enums::AsBool;
+use crate::enums::BinOp;
+use crate::enums::ComparisonOp;
+use crate::enums::Settings;
+use crate::enums::Value;
+use crate::unit::Quantity;
+use crate::unit::Unit;
+
+///
+/// 型をそろえた 2 つの数値
+///
+/// Int < BigInt < Rational < Float < Complex の順に、より広い方の型へそろえる
+/// Decimal は整数とだけそろえられ、有理数や浮動小数点数とは誤差を避けるため混ぜない
+///
+enum Pair {
+    Int(i32, i32),
+    BigInt(BigInt, BigInt),
+    Decimal(Decimal, Decimal),
+    Rational(BigRational, BigRational),
+    Float(f64, f64),
+    Complex(Complex64, Complex64),
+}
+
+/// 2 つの値を共通の数値型にそろえる。数値でなければ None を返す
+fn promote(lhs: &Value, rhs: &Value) -> Option<Pair> {
+    match (lhs, rhs) {
+        (Value::Int(l), Value::Int(r)) => Some(Pair::Int(*l, *r)),
+        (Value::Decimal(_), Value::Rational(_) | Value::Float(_) | Value::Complex(_)) | (Value::Rational(_) | Value::Float(_) | Value::Complex(_), Value::Decimal(_)) => None,
+        (Value::Decimal(_), _) | (_, Value::Decimal(_)) => Some(Pair::Decimal(to_decimal(lhs)?, to_decimal(rhs)?)),
+        (Value::Complex(_), _) | (_, Value::Complex(_)) => Some(Pair::Complex(to_complex(lhs)?, to_complex(rhs)?)),
+        (Value::Float(_), _) | (_, Value::Float(_)) => Some(Pair::Float(to_f64(lhs)?, to_f64(rhs)?)),
+        (Value::Rational(_), _) | (_, Value::Rational(_)) => Some(Pair::Rational(to_rational(lhs)?, to_rational(rhs)?)),
+        _ => Some(Pair::BigInt(to_bigint(lhs)?, to_bigint(rhs)?)),
+    }
+}
+
+fn to_bigint(value: &Value) -> Option<BigInt> {
+    match value {
+        Value::Int(i) => Some(BigInt::from(*i)),
+        Value::BigInt(n) => Some(n.clone()),
+        _ => None,
+    }
+}
+
+fn to_complex(value: &Value) -> Option<Complex64> {
+    match value {
+        Value::Complex(c) => Some(*c),
+        _ => Some(Complex64::new(to_f64(value)?, 0.0)),
+    }
+}
+
+fn to_decimal(value: &Value) -> Option<Decimal> {
+    match value {
+        Value::Int(i) => Some(Decimal::from(*i)),
+        Value::BigInt(n) => Decimal::from_str_exact(&n.to_string()).ok(),
+        Value::Decimal(d) => Some(*d),
+        _ => None,
+    }
+}
+
+fn to_rational(value: &Value) -> Option<BigRational> {
+    match value {
+        Value::Rational(r) => Some(r.clone()),
+        _ => Some(BigRational::from_integer(to_bigint(value)?)),
+    }
+}
+
+fn to_f64(value: &Value) -> Option<f64> {
+    match value {
+        Value::Int(i) => Some(*i as f64),
+        Value::BigInt(n) => n.to_f64(),
+        Value::Rational(r) => r.to_f64(),
+        Value::Decimal(d) => d.to_f64(),
+        Value::Float(f) => Some(*f),
+        _ => None,
+    }
+}
+
+///
+/// i32 どうしの演算を行い、桁あふれした場合は多倍長整数で計算し直す
+///
+fn int_op(l: i32, r: i32, checked: fn(i32, i32) -> Option<i32>, big: fn(BigInt, BigInt) -> BigInt) -> Value {
+    match checked(l, r) {
+        Some(i) => Value::Int(i),
+        None => Value::from_bigint(big(BigInt::from(l), BigInt::from(r))),
+    }
+}
+
+fn to_quantity(value: &Value) -> Option<Quantity> {
+    match value {
+        Value::Quantity(q) => Some(q.clone()),
+        _ => Some(Quantity {
+            value: to_f64(value)?,
+            unit: Unit::one(),
+        }),
+    }
+}
+
+/// 数値に単位をつける
+pub fn with_unit(value: Value, unit: &Unit) -> Result<Value, String> {
+    match (&value, to_f64(&value)) {
+        (Value::Complex(_), _) | (_, None) => Err(format!("{} に単位をつけることはできない", value)),
+        (_, Some(value)) => Ok(Value::Quantity(Quantity { value, unit: unit.clone() })),
+    }
+}
+
+///
+/// 単位つきの量の演算を行う
+///
+/// 加算、減算、剰余、切り捨て除算は右辺を左辺の単位に換算してから行い、次元が異なる場合はエラーとする
+/// べき乗の指数には無次元の整数だけを指定できる
+/// 結果が無次元になった場合は単位を外して浮動小数点数にする
+///
+fn quantity(op: &BinOp, lhs: &Value, rhs: &Value) -> Result<Value, String> {
+    let (l, r) = match (to_quantity(lhs), to_quantity(rhs)) {
+        (Some(l), Some(r)) => (l, r),
+        _ => return Err(format!("{} と {} の演算は未定義", lhs, rhs)),
+    };
+    let overflow = || format!("{} {} {} は単位の指数が大きすぎるため計算できない", lhs, symbol(op), rhs);
+    let result = match op {
+        BinOp::Add | BinOp::Sub | BinOp::Rem | BinOp::FloorDiv => {
+            let r = match r.convert(&l.unit) {
+                Some(r) => r,
+                None => return Err(format!("{} と {} は次元が異なるため計算できない", lhs, rhs)),
+            };
+            match op {
+                BinOp::Add => Quantity {
+                    value: l.value + r.value,
+                    unit: l.unit,
+                },
+                BinOp::Sub => Quantity {
+                    value: l.value - r.value,
+                    unit: l.unit,
+                },
+                _ if r.value == 0.0 => return Err(format!("{} {} {} の0での割り算が発生した", lhs, symbol(op), rhs)),
+                BinOp::Rem => Quantity {
+                    value: float_rem(l.value, r.value),
+                    unit: l.unit,
+                },
+                _ => return Ok(Value::Float((l.value / r.value).floor())),
+            }
+        }
+        BinOp::Pow => match r {
+            Quantity { value, unit } if unit.is_dimensionless() && unit.factor == 1.0 && value.fract() == 0.0 && value.abs() <= i32::MAX as f64 => Quantity {
+                value: l.value.powi(value as i32),
+                unit: l.unit.pow(value as i32).ok_or_else(overflow)?,
+            },
+            _ => return Err(format!("{} ** {} の指数には無次元の整数を指定する", lhs, rhs)),
+        },
+        BinOp::Mul => Quantity {
+            value: l.value * r.value,
+            unit: l.unit.mul(&r.unit).ok_or_else(overflow)?,
+        },
+        BinOp::Div if r.value == 0.0 => return Err(format!("{} / {} の0での割り算が発生した", lhs, rhs)),
+        BinOp::Div => Quantity {
+            value: l.value / r.value,
+            unit: l.unit.div(&r.unit).ok_or_else(overflow)?,
+        },
+        _ => return Err(format!("{} と {} の {} 演算は整数どうしでだけ計算できる", lhs, rhs, symbol(op))),
+    };
+    if result.unit.is_dimensionless() {
+        Ok(Value::Float(result.si()))
+    } else {
+        Ok(Value::Quantity(result))
+    }
+}
+
+/// 演算子の記号
+fn symbol(op: &BinOp) -> &'static str {
+    match op {
+        BinOp::Add => "+",
+        BinOp::Sub => "-",
+        BinOp::Mul => "*",
+        BinOp::Div => "/",
+        BinOp::FloorDiv => "//",
+        BinOp::Rem => "%",
+        BinOp::Pow => "**",
+        BinOp::BitAnd => "&",
+        BinOp::BitOr => "|",
+        BinOp::BitXor => "^",
+        BinOp::Shl => "<<",
+        BinOp::Shr => ">>",
+    }
+}
+
+/// 除数と同じ符号になる浮動小数点数の剰余を求める
+fn float_rem(l: f64, r: f64) -> f64 {
+    let rem = l % r;
+    if rem != 0.0 && (rem < 0.0) != (r < 0.0) {
+        rem + r
+    } else {
+        rem
+    }
+}
+
+///
+/// 整数や有理数の整数乗を誤差なく求める
+///
+/// 巨大な整数を作らないよう、結果の分子と分母はそれぞれ MAX_POW_BITS ビット程度までに制限し、超える場合は None を返す
+///
+fn rational_pow(base: BigRational, e: i32) -> Option<BigRational> {
+    const MAX_POW_BITS: u64 = 1 << 20;
+    // 絶対値が 0 か 1 の場合は何乗しても大きくならない
+    let bits = |n: &BigInt| if n.abs().is_one() { 0 } else { n.bits() };
+    if bits(base.numer()).max(bits(base.denom())) * u64::from(e.unsigned_abs()) > MAX_POW_BITS {
+        return None;
+    }
+    Some(base.pow(e))
+}
+
+/// 小数の整数乗を求める。桁あふれした場合は None を返す
+fn decimal_pow(d: Decimal, e: i32) -> Option<Decimal> {
+    let (mut base, mut n, mut result) = (d, e.unsigned_abs(), Decimal::ONE);
+    while n > 0 {
+        if n & 1 == 1 {
+            result = result.checked_mul(base)?;
+        }
+        n >>= 1;
+        if n > 0 {
+            base = base.checked_mul(base)?;
+        }
+    }
+    if e < 0 {
+        Decimal::ONE.checked_div(result)
+    } else {
+        Some(result)
+    }
+}
+
+///
+/// 小数の演算結果を設定された桁数に丸める
+///
+fn decimal_op(result: Option<Decimal>, settings: &Settings) -> Result<Value, String> {
+    match result {
+        Some(d) => {
+            let mut d = d.round_dp_with_strategy(settings.scale, settings.rounding.strategy());
+            d.rescale(settings.scale);
+            Ok(Value::Decimal(d))
+        }
+        None => Err("小数の演算で桁あふれが発生した".to_string()),
+    }
+}
+
+///
+/// 二項演算を行う
+///
+/// settings.rational が有効な場合、整数どうしの除算は切り捨てずに有理数を返す
+/// 小数の演算結果は settings.scale 桁に settings.rounding の方法で丸める
+///
+/// 切り捨て除算 `//` は負の無限大方向へ切り捨て、剰余 `%` は除数と同じ符号になる
+/// （`a == (a // b) * b + a % b` が成り立つ）
+/// 整数の負の指数によるべき乗は、有理数モードでなければエラーになる
+///
+pub fn binary(op: &BinOp, lhs: Value, rhs: Value, settings: &Settings) -> Result<Value, String> {
+    if let (Value::Quantity(_), _) | (_, Value::Quantity(_)) = (&lhs, &rhs) {
+        return quantity(op, &lhs, &rhs);
+    }
+    let result = match op {
+        BinOp::Add => match (lhs, rhs) {
+            (Value::String(lhs_s), Value::String(rhs_s)) => Ok(Value::String(lhs_s + &rhs_s)),
+            // リストの連結は新しいリストを作る
+            (Value::List(l), Value::List(r)) => {
+                let mut items = l.borrow().clone();
+                items.extend(r.borrow().iter().cloned());
+                Ok(Value::list(items))
+            }
+            (lhs, rhs) => match promote(&lhs, &rhs) {
+                Some(Pair::Int(l, r)) => Ok(int_op(l, r, i32::checked_add, |l, r| l + r)),
+                Some(Pair::BigInt(l, r)) => Ok(Value::from_bigint(l + r)),
+                Some(Pair::Decimal(l, r)) => decimal_op(l.checked_add(r), settings),
+                Some(Pair::Rational(l, r)) => Ok(Value::from_rational(l + r)),
+                Some(Pair::Float(l, r)) => Ok(Value::Float(l + r)),
+                Some(Pair::Complex(l, r)) => Ok(Value::Complex(l + r)),
+                None => Err("加算処理が未定義".to_string()),
+            },
+        },
+        BinOp::Sub => match promote(&lhs, &rhs) {
+            Some(Pair::Int(l, r)) => Ok(int_op(l, r, i32::checked_sub, |l, r| l - r)),
+            Some(Pair::BigInt(l, r)) => Ok(Value::from_bigint(l - r)),
+            Some(Pair::Decimal(l, r)) => decimal_op(l.checked_sub(r), settings),
+            Some(Pair::Rational(l, r)) => Ok(Value::from_rational(l - r)),
+            Some(Pair::Float(l, r)) => Ok(Value::Float(l - r)),
+            Some(Pair::Complex(l, r)) => Ok(Value::Complex(l - r)),
+            None => Err("減算処理が未定義".to_string()),
+        },
+        BinOp::Mul => match promote(&lhs, &rhs) {
+            Some(Pair::Int(l, r)) => Ok(int_op(l, r, i32::checked_mul, |l, r| l * r)),
+            Some(Pair::BigInt(l, r)) => Ok(Value::from_bigint(l * r)),
+            Some(Pair::Decimal(l, r)) => decimal_op(l.checked_mul(r), settings),
+            Some(Pair::Rational(l, r)) => Ok(Value::from_rational(l * r)),
+            Some(Pair::Float(l, r)) => Ok(Value::Float(l * r)),
+            Some(Pair::Complex(l, r)) => Ok(Value::Complex(l * r)),
+            None => Err("乗算処理が未定義".to_string()),
+        },
+        // 整数どうしの除算は 0 方向へ切り捨てる（有理数モードを除く）
+        BinOp::Div => match promote(&lhs, &rhs) {
+            Some(Pair::Int(_, 0)) | Some(Pair::Float(_, 0.0)) => Err(format!("{} / {} の0での割り算が発生した", lhs, rhs)),
+            Some(Pair::BigInt(_, r)) if r.is_zero() => Err(format!("{} / {} の0での割り算が発生した", lhs, rhs)),
+            Some(Pair::Complex(_, r)) if r.is_zero() => Err(format!("{} / {} の0での割り算が発生した", lhs, rhs)),
+            Some(Pair::Decimal(_, r)) if r.is_zero() => Err(format!("{} / {} の0での割り算が発生した", lhs, rhs)),
+            Some(Pair::Rational(_, r)) if r.is_zero() => Err(format!("{} / {} の0での割り算が発生した", lhs, rhs)),
+            Some(Pair::Int(l, r)) if settings.rational => Ok(Value::from_rational(BigRational::new(l.into(), r.into()))),
+            Some(Pair::BigInt(l, r)) if settings.rational => Ok(Value::from_rational(BigRational::new(l, r))),
+            Some(Pair::Int(l, r)) => Ok(int_op(l, r, i32::checked_div, |l, r| l / r)),
+            Some(Pair::BigInt(l, r)) => Ok(Value::from_bigint(l / r)),
+            Some(Pair::Decimal(l, r)) => decimal_op(l.checked_div(r), settings),
+            Some(Pair::Rational(l, r)) => Ok(Value::from_rational(l / r)),
+            Some(Pair::Float(l, r)) => Ok(Value::Float(l / r)),
+            Some(Pair::Complex(l, r)) => Ok(Value::Complex(l / r)),
+            None => Err("除算処理が未定義".to_string()),
+        },
+        BinOp::FloorDiv | BinOp::Rem => {
+            let zero = format!("{} {} {} の0での割り算が発生した", lhs, symbol(op), rhs);
+            let floor = *op == BinOp::FloorDiv;
+            match promote(&lhs, &rhs) {
+                Some(Pair::Int(_, 0)) | Some(Pair::Float(_, 0.0)) => Err(zero),
+                Some(Pair::BigInt(_, r)) if r.is_zero() => Err(zero),
+                Some(Pair::Decimal(_, r)) if r.is_zero() => Err(zero),
+                Some(Pair::Rational(_, r)) if r.is_zero() => Err(zero),
+                // i32::MIN // -1 のように桁あふれする場合は多倍長整数で計算し直す
+                Some(Pair::Int(l, r)) if floor => Ok(int_op(l, r, |l, r| l.checked_div(r).map(|_| Integer::div_floor(&l, &r)), |l, r| l.div_floor(&r))),
+                Some(Pair::Int(l, r)) => Ok(int_op(l, r, |l, r| l.checked_rem(r).map(|_| l.mod_floor(&r)), |l, r| l.mod_floor(&r))),
+                Some(Pair::BigInt(l, r)) if floor => Ok(Value::from_bigint(l.div_floor(&r))),
+                Some(Pair::BigInt(l, r)) => Ok(Value::from_bigint(l.mod_floor(&r))),
+                Some(Pair::Decimal(l, r)) => {
+                    let q = l.checked_div(r).map(|q| q.floor());
+                    decimal_op(if floor { q } else { q.and_then(|q| q.checked_mul(r)).and_then(|m| l.checked_sub(m)) }, settings)
+                }
+                Some(Pair::Rational(l, r)) => {
+                    let q = (&l / &r).floor();
+                    Ok(Value::from_rational(if floor { q } else { l - r * q }))
+                }
+                Some(Pair::Float(l, r)) if floor => Ok(Value::Float((l / r).floor())),
+                Some(Pair::Float(l, r)) => Ok(Value::Float(float_rem(l, r))),
+                Some(Pair::Complex(_, _)) => Err(format!("複素数の {} 演算は未定義", symbol(op))),
+                None => Err(format!("{} と {} の {} 演算は未定義", lhs, rhs, symbol(op))),
+            }
+        }
+        BinOp::Pow => match (&lhs, &rhs) {
+            (Value::Int(_) | Value::BigInt(_) | Value::Rational(_), Value::Int(e)) => {
+                let base = to_rational(&lhs).unwrap();
+                if base.is_zero() && *e < 0 {
+                    Err(format!("{} ** {} の0での割り算が発生した", lhs, rhs))
+                } else if base.is_integer() && *e < 0 && !settings.rational {
+                    Err(format!("{} ** {} は負の指数のため整数では計算できない", lhs, rhs))
+                } else if let (Some(width), true) = (settings.wrap, base.is_integer() && *e >= 0) {
+                    // 固定幅の設定では冪剰余で求め、巨大な整数を作らない
+                    Ok(Value::from_bigint(base.to_integer().modpow(&BigInt::from(*e), &(BigInt::one() << width.bits()))))
+                } else {
+                    rational_pow(base, *e).map(Value::from_rational).ok_or_else(|| format!("{} ** {} は結果が大きすぎるため計算できない", lhs, rhs))
+                }
+            }
+            (Value::Decimal(d), Value::Int(e)) if d.is_zero() && *e < 0 => Err(format!("{} ** {} の0での割り算が発生した", lhs, rhs)),
+            (Value::Decimal(d), Value::Int(e)) => decimal_op(decimal_pow(*d, *e), settings),
+            _ => match promote(&lhs, &rhs) {
+                Some(Pair::Complex(l, r)) => Ok(Value::Complex(l.powc(r))),
+                Some(Pair::Decimal(_, _)) => Err(format!("{} ** {} の指数には i32 に収まる整数を指定する", lhs, rhs)),
+                Some(_) => match (to_f64(&lhs), to_f64(&rhs)) {
+                    (Some(l), Some(r)) => Ok(Value::Float(l.powf(r))),
+                    _ => Err(format!("{} ** {} を計算できない", lhs, rhs)),
+                },
+                None => Err("べき乗が未定義".to_string()),
+            },
+        },
+        BinOp::BitAnd | BinOp::BitOr | BinOp::BitXor | BinOp::Shl | BinOp::Shr => match (to_bigint(&lhs), to_bigint(&rhs)) {
+            (Some(l), Some(r)) => bitwise(op, l, r, settings),
+            _ => Err(format!("{} と {} の {} 演算は整数どうしでだけ計算できる", lhs, rhs, symbol(op))),
+        },
+    }?;
+    Ok(wrap(result, settings))
+}
+
+///
+/// 整数どうしのビット演算を行う。負の数は 2 の補数で表したものとして扱う
+///
+/// 固定幅の設定が有効な場合、シフト量は幅のビット数で割った余りにする
+/// そうでない場合、巨大な整数を作らないようにシフト量は MAX_SHIFT までに制限する
+///
+fn bitwise(op: &BinOp, l: BigInt, r: BigInt, settings: &Settings) -> Result<Value, String> {
+    const MAX_SHIFT: usize = 65536;
+    let shift = || {
+        if r.is_negative() {
+            return Err(format!("シフト量には 0 以上の整数を指定するが {} が指定された", r));
+        }
+        match (settings.wrap, r.to_usize()) {
+            (Some(width), _) => Ok((&r & BigInt::from(width.bits() - 1)).to_usize().unwrap()),
+            (None, Some(n)) if n <= MAX_SHIFT => Ok(n),
+            _ => Err(format!("シフト量には {} 以下の整数を指定するが {} が指定された", MAX_SHIFT, r)),
+        }
+    };
+    let result = match op {
+        BinOp::BitAnd => &l & &r,
+        BinOp::BitOr => &l | &r,
+        BinOp::BitXor => &l ^ &r,
+        BinOp::Shl => &l << shift()?,
+        // 右シフトは負の無限大方向へ切り捨てる（算術シフト）
+        _ => &l >> shift()?,
+    };
+    Ok(Value::from_bigint(result))
+}
+
+/// 固定幅の設定が有効な場合、整数の演算結果を幅に収まるよう折り返す
+pub fn wrap(value: Value, settings: &Settings) -> Value {
+    match (settings.wrap, to_bigint(&value)) {
+        (Some(width), Some(n)) => Value::from_bigint(width.wrap(&n)),
+        _ => value,
+    }
+}
+
+///
+/// 符号を反転する
+///
+pub fn negate(value: Value, settings: &Settings) -> Result<Value, String> {
+    match value {
+        Value::Int(i) => Ok(wrap(int_op(0, i, i32::checked_sub, |l, r| l - r), settings)),
+        Value::BigInt(n) => Ok(wrap(Value::from_bigint(-n), settings)),
+        Value::Rational(r) => Ok(Value::Rational(-r)),
+        Value::Decimal(d) => Ok(Value::Decimal(-d)),
+        Value::Float(f) => Ok(Value::Float(-f)),
+        Value::Complex(c) => Ok(Value::Complex(-c)),
+        Value::Quantity(q) => Ok(Value::Quantity(Quantity { value: -q.value, unit: q.unit })),
+        v => Err(format!("{} の符号は反転できない", v)),
+    }
+}
+
+///
+/// 整数のビットを反転する（`~x` は `-x - 1` と等しい）
+///
+pub fn bit_not(value: Value, settings: &Settings) -> Result<Value, String> {
+    match to_bigint(&value) {
+        Some(n) => Ok(wrap(Value::from_bigint(!n), settings)),
+        None => Err(format!("{} のビットは反転できない", value)),
+    }
+}
+
+///
+/// 論理演算の被演算子を真偽値として解釈する
+///
+/// strict が有効な場合、真偽値以外の値はエラーになる
+///
+pub fn truth(value: &Value, settings: &Settings) -> Result<bool, String> {
+    match value {
+        Value::Bool(b) => Ok(*b),
+        v if settings.strict => Err(format!("論理演算に真偽値ではない値 {} が指定された", v)),
+        v => Ok(v.as_bool()),
+    }
+}
+
+///
+/// 2 つの値が等しいかどうかを返す
+///
+/// 数値どうしは型をそろえて値で比べ、単位つきの量は SI 基本単位に換算して比べる
+/// リスト、辞書、構造体は要素ごとに比べる。型が異なり比べられない値どうしは等しくない
+///
+pub fn equal(lhs: &Value, rhs: &Value) -> bool { equal_in(lhs, rhs, &mut Vec::new()) }
+
+/// 比較中のリスト・辞書・構造体の組を visiting に積みながら 2 つの値が等しいかどうかを返す
+fn equal_in(lhs: &Value, rhs: &Value, visiting: &mut Vec<(*const (), *const ())>) -> bool {
+    match (lhs, rhs) {
+        (Value::List(l), Value::List(r)) => visit_pair((Rc::as_ptr(l) as *const (), Rc::as_ptr(r) as *const ()), visiting, |visiting| {
+            let (l, r) = (l.borrow(), r.borrow());
+            l.len() == r.len() && l.iter().zip(r.iter()).all(|(l, r)| equal_in(l, r, visiting))
+        }),
+        (Value::Map(l), Value::Map(r)) => visit_pair((Rc::as_ptr(l) as *const (), Rc::as_ptr(r) as *const ()), visiting, |visiting| {
+            let (l, r) = (l.borrow(), r.borrow());
+            l.len() == r.len() && l.iter().zip(r.iter()).all(|((lk, lv), (rk, rv))| lk == rk && equal_in(lv, rv, visiting))
+        }),
+        (Value::Struct(l), Value::Struct(r)) => visit_pair((Rc::as_ptr(l) as *const (), Rc::as_ptr(r) as *const ()), visiting, |visiting| {
+            let (l, r) = (l.borrow(), r.borrow());
+            l.name == r.name && l.fields.iter().zip(r.fields.iter()).all(|((lf, lv), (rf, rv))| lf == rf && equal_in(lv, rv, visiting))
+        }),
+        (Value::Quantity(_), _) | (_, Value::Quantity(_)) => match (to_quantity(lhs), to_quantity(rhs)) {
+            (Some(l), Some(r)) => l.unit.dims == r.unit.dims && l.si() == r.si(),
+            _ => false,
+        },
+        _ => match promote(lhs, rhs) {
+            Some(Pair::Int(l, r)) => l == r,
+            Some(Pair::BigInt(l, r)) => l == r,
+            Some(Pair::Decimal(l, r)) => l == r,
+            Some(Pair::Rational(l, r)) => l == r,
+            Some(Pair::Float(l, r)) => l == r,
+            Some(Pair::Complex(l, r)) => l == r,
+            // 文字列や真偽値は同じ型の値とだけ等しくなりうる
+            None => lhs == rhs,
+        },
+    }
+}
+
+///
+/// 共有される値の組 pair の要素を body で比較する
+///
+/// 同じ値どうしは等しい。比較中の組に再び出会った場合は循環しているため、その組は等しいとみなして残りの要素で判定する
+///
+fn visit_pair(pair: (*const (), *const ()), visiting: &mut Vec<(*const (), *const ())>, body: impl FnOnce(&mut Vec<(*const (), *const ())>) -> bool) -> bool {
+    if pair.0 == pair.1 || visiting.contains(&pair) {
+        return true;
+    }
+    visiting.push(pair);
+    let result = body(visiting);
+    visiting.pop();
+    result
+}
+
+/// 2 つの値の大小関係を返す。NaN との比較のように順序が決まらない場合は None を返す
+fn partial_order(lhs: &Value, rhs: &Value) -> Result<Option<Ordering>, String> {
+    if let (Value::String(l), Value::String(r)) = (lhs, rhs) {
+        return Ok(Some(l.cmp(r)));
+    }
+
+    // 単位つきの量は SI 基本単位に換算して比較する
+    if let (Value::Quantity(_), _) | (_, Value::Quantity(_)) = (lhs, rhs) {
+        return match (to_quantity(lhs), to_quantity(rhs)) {
+            (Some(l), Some(r)) if l.unit.dims == r.unit.dims => Ok(l.si().partial_cmp(&r.si())),
+            _ => Err(format!("{} と {} は次元が異なるため比較できない", lhs, rhs)),
+        };
+    }
+    match promote(lhs, rhs) {
+        Some(Pair::Int(l, r)) => Ok(Some(l.cmp(&r))),
+        Some(Pair::BigInt(l, r)) => Ok(Some(l.cmp(&r))),
+        Some(Pair::Decimal(l, r)) => Ok(Some(l.cmp(&r))),
+        Some(Pair::Rational(l, r)) => Ok(Some(l.cmp(&r))),
+        Some(Pair::Float(l, r)) => Ok(l.partial_cmp(&r)),
+        // 複素数には大小関係がない
+        Some(Pair::Complex(_, _)) => Err("複素数の大小比較は未定義".to_string()),
+        None => Err(format!("{} と {} の大小比較は未定義", lhs, rhs)),
+    }
+}
+
+///
+/// 比較を行う
+///
+/// 等しいかどうかはどの値どうしでも判定でき、大小は数値どうしか文字列どうしでだけ判定できる
+///
+pub fn compare(op: &ComparisonOp, lhs: Value, rhs: Value) -> Result<Value, String> {
+    let result = match op {
+        ComparisonOp::Eq => equal(&lhs, &rhs),
+        ComparisonOp::Ne => !equal(&lhs, &rhs),
+        _ => match (op, partial_order(&lhs, &rhs)?) {
+            (_, None) => false,
+            (ComparisonOp::Lt, Some(ordering)) => ordering == Ordering::Less,
+            (ComparisonOp::Gt, Some(ordering)) => ordering == Ordering::Greater,
+            (ComparisonOp::Le, Some(ordering)) => ordering != Ordering::Greater,
+            (_, Some(ordering)) => ordering != Ordering::Less,
+        },
+    };
+    Ok(Value::Bool(result))
+}
+
+///
+/// 2 つの値の順序を返す
+///
+/// 文字列どうしは辞書順、数値どうしは大小で比べる。比較できない場合はエラーになる
+///
+pub fn order(lhs: &Value, rhs: &Value) -> Result<Ordering, String> { Ok(partial_order(lhs, rhs)?.unwrap_or(Ordering::Equal)) }
+
+///
+/// 並べ替えのための 2 つの値の順序を返す
+///
+/// order と異なり、浮動小数点数は NaN も含めて f64::total_cmp で比べるため全順序になる。比較できない場合はエラーになる
+///
+pub fn total_order(lhs: &Value, rhs: &Value) -> Result<Ordering, String> {
+    let ordering = partial_order(lhs, rhs)?;
+    if let (Value::Quantity(_), _) | (_, Value::Quantity(_)) = (lhs, rhs) {
+        if let (Some(l), Some(r)) = (to_quantity(lhs), to_quantity(rhs)) {
+            return Ok(l.si().total_cmp(&r.si()));
+        }
+    }
+    match promote(lhs, rhs) {
+        Some(Pair::Float(l, r)) => Ok(l.total_cmp(&r)),
+        _ => Ok(ordering.unwrap_or(Ordering::Equal)),
+    }
+}
+
+/// 添字を 0 以上 len 以下の位置に変換する
+fn position(index: &Value, len: usize) -> Result<usize, String> {
+    match index {
+        Value::Int(i) if *i >= 0 && *i as usize <= len => Ok(*i as usize),
+        Value::Int(_) | Value::BigInt(_) => Err(format!("インデックス {} は範囲外（長さ {}）", index, len)),
+        v => Err(format!("インデックスには整数を指定するが {} が指定された", v)),
+    }
+}
+
+///
+/// 添字で要素を取り出す（xs[i], m["key"], m.key）
+///
+pub fn index(target: Value, index: Value) -> Result<Value, String> {
+    match target {
+        Value::Struct(r) => {
+            let r = r.borrow();
+            match r.fields.iter().find(|(field, _)| Value::String(field.clone()) == index) {
+                Some((_, value)) => Ok(value.clone()),
+                None => Err(format!("構造体 {} にフィールド {} は存在しない", r.name, index)),
+            }
+        }
+        Value::Map(m) => match index {
+            Value::String(key) => match m.borrow().get(&key) {
+                Some(value) => Ok(value.clone()),
+                None => Err(format!("キー {:?} が存在しない", key)),
+            },
+            v => Err(format!("辞書のキーには文字列を指定するが {} が指定された", v)),
+        },
+        Value::List(l) => {
+            let items = l.borrow();
+            match position(&index, items.len())? {
+                i if i < items.len() => Ok(items[i].clone()),
+                _ => Err(format!("インデックス {} は範囲外（長さ {}）", index, items.len())),
+            }
+        }
+        v => Err(format!("{} は添字で参照できない", v)),
+    }
+}
+
+///
+/// 添字の位置の要素を書き換える（xs[i] = v, m.key = v）
+///
+/// 辞書に存在しないキーを指定した場合は追加する。構造体のフィールドは追加できない
+///
+pub fn set_index(target: Value, index: Value, value: Value) -> Result<(), String> {
+    match target {
+        Value::Struct(r) => {
+            let mut r = r.borrow_mut();
+            let name = r.name.clone();
+            match r.fields.iter_mut().find(|(field, _)| Value::String(field.clone()) == index) {
+                Some((_, v)) => *v = value,
+                None => return Err(format!("構造体 {} にフィールド {} は存在しない", name, index)),
+            }
+        }
+        Value::List(l) => {
+            let mut items = l.borrow_mut();
+            match position(&index, items.len())? {
+                i if i < items.len() => items[i] = value,
+                _ => return Err(format!("インデックス {} は範囲外（長さ {}）", index, items.len())),
+            }
+        }
+        Value::Map(m) => match index {
+            Value::String(key) => {
+                m.borrow_mut().insert(key, value);
+            }
+            v => return Err(format!("辞書のキーには文字列を指定するが {} が指定された", v)),
+        },
+        v => return Err(format!("{} の要素には代入できない", v)),
+    }
+    Ok(())
+}
+
+///
+/// 範囲の要素を新しいリストとして取り出す（xs[a..b]）
+///
+/// start を省略すると先頭から、end を省略すると末尾までになる
+///
+pub fn slice(target: Value, start: Option<Value>, end: Option<Value>) -> Result<Value, String> {
+    match target {
+        Value::List(l) => {
+            let items = l.borrow();
+            let from = match start {
+                Some(start) => position(&start, items.len())?,
+                None => 0,
+            };
+            let to = match end {
+                Some(end) => position(&end, items.len())?,
+                None => items.len(),
+            };
+            if from > to {
+                return Err(format!("範囲 {}..{} の開始が終了より後にある", from, to));
+            }
+            Ok(Value::list(items[from..to].to_vec()))
+        }
+        v => Err(format!("{} は範囲で参照できない", v)),
+    }
+}
@@ -1,31 +1,70 @@
+mod diagnostic;
 mod enums;
+mod env;
 mod evaluator;
 mod parser;
 mod scanner;
 mod tests;
+mod unit;
 
-use crate::enums::Env;
 use crate::enums::FunctionTable;
+use crate::enums::Settings;
+use crate::enums::Value;
+use crate::env::Env;
 
-fn print_eval_result(str: &str) -> () {
-    print!("-----------------------------------------\n");
-    print!("計算対象：\n {:?}\n", str);
-    print!("スキャン結果：\n {:?}\n", scanner::scanner(str));
-    print!(
-        "パース結果：\n {:?}\n",
-        parser::parser(scanner::scanner(str))
-    );
+/// プログラムを実行するスレッドのスタックの大きさ。関数呼び出しが上限の深さまで再帰できるようにする
+const STACK_SIZE: usize = 512 * 1024 * 1024;
 
-    let mut env = Env::new();
+fn print_eval_result(str: &str, settings: Settings) {
+    println!("-----------------------------------------");
+    println!("計算対象：\n {:?}", str);
+
+    let tokens = match scanner::scanner(str) {
+        Ok(tokens) => tokens,
+        Err(error) => {
+            println!("スキャンエラー：");
+            for item in &error.items {
+                print!("{}", diagnostic::render(str, item));
+            }
+            return;
+        }
+    };
+    println!("スキャン結果：\n {:?}", tokens);
+
+    let syntax = match parser::parser(tokens) {
+        Ok(syntax) => syntax,
+        Err(errors) => {
+            println!("パースエラー：");
+            for error in &errors {
+                print!("{}", diagnostic::render(str, error));
+            }
+            return;
+        }
+    };
+    println!("パース結果：\n {:?}", syntax);
+
+    let env = Env::new();
+    env.update_settings(|s| *s = settings);
     let mut ft = FunctionTable::new();
-    print!("実行：\n");
-    evaluator::eval(parser::parser(scanner::scanner(str)), &mut env, &mut ft);
-
-    print!(
-        "実行後の状態：\n　環境：{:?}\n　関数テーブル：{:?}\n",
-        env, ft
-    );
-    print!("-----------------------------------------\n");
+    println!("実行：");
+    if let Err(error) = evaluator::eval(syntax, &env, &mut ft) {
+        print!("{}", diagnostic::render(str, &error));
+    }
+
+    println!("実行後の状態：\n　環境：{:?}\n　関数テーブル：{:?}", env, ft);
+    println!("-----------------------------------------");
+}
+
+/// コマンドライン引数で指定された設定値を値に変換する
+fn parse_flag_value(value: &str) -> Value {
+    match value {
+        "true" => Value::Bool(true),
+        "false" => Value::Bool(false),
+        _ => match value.parse() {
+            Ok(i) => Value::Int(i),
+            Err(_) => Value::String(value.to_string()),
+        },
+    }
 }
 
 fn main() {
@@ -33,13 +72,34 @@ fn main() {
     #[global_allocator]
     static ALLOC: dhat::Alloc = dhat::Alloc;
 
-    let args: Vec<String> = std::env::args().collect();
-    match args.get(1) {
-        Some(str) => print_eval_result(str),
-        _ => print!("usage: cargo run \"x = 1 + 2 + 3; print(x)\"\n"),
+    // "--name" もしくは "--name=value" の形式の引数で設定を変更する
+    let mut settings = Settings::default();
+    let mut program = None;
+    for arg in std::env::args().skip(1) {
+        match arg.strip_prefix("--") {
+            Some(flag) => {
+                let (name, value) = match flag.split_once('=') {
+                    Some((name, value)) => (name, parse_flag_value(value)),
+                    None => (flag, Value::Bool(true)),
+                };
+                if let Err(message) = settings.set(name, value) {
+                    println!("{}", message);
+                    return;
+                }
+            }
+            None => program = Some(arg),
+        }
+    }
+
+    match program {
+        Some(str) => {
+            // 深い再帰で落ちないよう、大きなスタックを持つスレッドで実行する
+            let runner = std::thread::Builder::new().stack_size(STACK_SIZE).spawn(move || print_eval_result(&str, settings));
+            runner.expect("実行用のスレッドを作成できない").join().expect("実行用のスレッドが異常終了した");
+        }
+        _ => println!("usage: cargo run [-- --rational] \"x = 1 + 2 + 3; print(x)\""),
     }
 
     #[cfg(feature = "dhat-heap")]
     let _profiler = dhat::Profiler::new_heap();
 }
-
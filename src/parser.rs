@@ -1,55 +1,136 @@
-mod addexpr;
-mod mulexpr;
+mod binaryexpr;
 mod paramlist;
 mod primaryexpr;
-mod relationalexpr;
 mod state;
 
+use crate::enums::ParseError;
+use crate::enums::Span;
+use crate::enums::SpannedToken;
 use crate::enums::Syntax;
 use crate::enums::Token;
 
 struct Parser {
-    input: Vec<Token>,
+    input: Vec<SpannedToken>,
     pos: usize,
+    // 現在読んでいるブロック '{' Statement '}' の深さ
+    depth: usize,
+    // 現在読んでいるループの深さ（関数の中では 0 から数え直す）
+    loops: usize,
+    errors: Vec<ParseError>,
 }
 
 impl Parser {
-    fn new(tokens: Vec<Token>) -> Self {
+    fn new(tokens: Vec<SpannedToken>) -> Self {
         Parser {
             input: tokens,
             pos: 0,
+            depth: 0,
+            loops: 0,
+            errors: Vec::new(),
         }
     }
 
     fn current(&self) -> Option<Token> {
         if self.pos < self.input.len() {
-            return Some(self.input[self.pos].clone());
+            return Some(self.input[self.pos].token.clone());
         }
         None
     }
 
-    fn next(&self) -> Option<Token> {
-        if self.pos + 1 < self.input.len() {
-            return Some(self.input[self.pos + 1].clone());
+    /// n 個先のトークンを返却する
+    fn peek(&self, n: usize) -> Option<Token> { self.input.get(self.pos + n).map(|t| t.token.clone()) }
+
+    ///
+    /// 現在のトークンの位置を返却する
+    ///
+    /// 入力の終わりでは最後のトークンの直後を指す
+    ///
+    fn current_span(&self) -> Span {
+        match (self.input.get(self.pos), self.input.last()) {
+            (Some(token), _) => token.span,
+            (None, Some(last)) => Span {
+                start: last.span.end,
+                end: last.span.end,
+                line: last.span.line,
+                col: last.span.col + (last.span.end - last.span.start),
+            },
+            (None, None) => Span {
+                line: 1,
+                col: 1,
+                ..Span::default()
+            },
+        }
+    }
+
+    /// start から直前に読んだトークンまでの範囲を返却する
+    fn span_from(&self, start: Span) -> Span {
+        match self.pos.checked_sub(1).and_then(|i| self.input.get(i)) {
+            Some(token) => start.to(token.span),
+            None => start,
+        }
+    }
+
+    /// 現在位置を指す長さ 0 の範囲を返却する
+    fn empty_span(&self) -> Span {
+        let span = self.current_span();
+        Span {
+            end: span.start,
+            ..span
         }
-        None
     }
 
     fn fix(&mut self) { self.pos += 1; }
 
-    fn confirm(&mut self, expect: Token) {
+    /// 現在のトークンに対するエラーを作成する
+    fn error(&self, expected: Vec<String>) -> ParseError {
+        ParseError {
+            expected,
+            found: self.current(),
+            span: self.current_span(),
+        }
+    }
+
+    fn confirm(&mut self, expect: Token) -> Result<(), ParseError> {
         match self.current() {
-            Some(token) if token == expect => self.fix(),
-            _ => panic!(
-                "{:?} を想定していたが想定外のトークン {:?} がきた",
-                expect,
-                self.current()
-            ),
-        };
+            Some(token) if token == expect => {
+                self.fix();
+                Ok(())
+            }
+            _ => Err(self.error(vec![expect.to_string()])),
+        }
+    }
+
+    ///
+    /// エラーから復帰するため、文の区切りまで読み飛ばす
+    ///
+    /// ';' もしくは現在のブロックを閉じる '}' の手前で止まる
+    ///
+    fn synchronize(&mut self) {
+        let mut depth = 0;
+        while let Some(token) = self.current() {
+            match token {
+                Token::SEMICOLON if depth == 0 => return,
+                Token::RBRACE if depth == 0 && self.depth > 0 => return,
+                Token::LBRACE => depth += 1,
+                Token::RBRACE if depth > 0 => depth -= 1,
+                _ => {}
+            }
+            self.fix();
+        }
     }
 }
 
-pub fn parser(toks: Vec<Token>) -> Syntax {
+///
+/// トークン列を構文木に変換する
+///
+/// エラーがあっても文の区切りで復帰して解析を続け、検出したエラーをすべて返却する
+///
+pub fn parser(toks: Vec<SpannedToken>) -> Result<Syntax, Vec<ParseError>> {
     let mut parser = Parser::new(toks);
-    return Syntax::Statement(parser.parse_state());
+    let st = parser.parse_state();
+    if parser.errors.is_empty() {
+        Ok(Syntax::Statement(st))
+    } else {
+        Err(parser.errors)
+    }
 }
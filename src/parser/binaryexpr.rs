@@ -0,0 +1,153 @@
+use crate::parser::Parser;
+
+use num_bigint::BigInt;
+
+use crate::enums::BinOp;
+use crate::enums::ComparisonOp;
+use crate::enums::Expr;
+use crate::enums::ExprKind;
+use crate::enums::LogicalOp;
+use crate::enums::ParseError;
+use crate::enums::Token;
+
+///
+/// 結合性
+///
+/// * Left - 左結合。`a - b - c` は `(a - b) - c` になる
+/// * Right - 右結合。`a ** b ** c` は `a ** (b ** c)` になる
+/// * Chain - 連結。`a < b <= c` は `a < b && b <= c` と同じ意味になり、b は 1 回だけ評価する
+///
+#[derive(Clone, Copy, PartialEq)]
+enum Assoc {
+    Left,
+    Right,
+    Chain,
+}
+
+/// 二項演算子が表す演算
+#[derive(Clone)]
+enum Operator {
+    Binary(BinOp),
+    Comparison(ComparisonOp),
+    Logical(LogicalOp),
+}
+
+///
+/// 二項演算子の表
+///
+/// トークン、優先順位（大きいほど強く結合する）、結合性、演算の組
+///
+static OPERATOR_TABLE: [(Token, u8, Assoc, Operator); 20] = [
+    (Token::OROR, 1, Assoc::Left, Operator::Logical(LogicalOp::Or)),
+    (Token::ANDAND, 2, Assoc::Left, Operator::Logical(LogicalOp::And)),
+    (Token::EQEQ, 3, Assoc::Chain, Operator::Comparison(ComparisonOp::Eq)),
+    (Token::NOTEQ, 3, Assoc::Chain, Operator::Comparison(ComparisonOp::Ne)),
+    (Token::LT, 3, Assoc::Chain, Operator::Comparison(ComparisonOp::Lt)),
+    (Token::GT, 3, Assoc::Chain, Operator::Comparison(ComparisonOp::Gt)),
+    (Token::LE, 3, Assoc::Chain, Operator::Comparison(ComparisonOp::Le)),
+    (Token::GE, 3, Assoc::Chain, Operator::Comparison(ComparisonOp::Ge)),
+    // 演算子の位置にある '|' はビット単位の論理和（式の先頭ではラムダ式になる）
+    (Token::PIPE, 4, Assoc::Left, Operator::Binary(BinOp::BitOr)),
+    (Token::CARET, 5, Assoc::Left, Operator::Binary(BinOp::BitXor)),
+    (Token::AMP, 6, Assoc::Left, Operator::Binary(BinOp::BitAnd)),
+    (Token::SHL, 7, Assoc::Left, Operator::Binary(BinOp::Shl)),
+    (Token::SHR, 7, Assoc::Left, Operator::Binary(BinOp::Shr)),
+    (Token::PLUS, 8, Assoc::Left, Operator::Binary(BinOp::Add)),
+    (Token::MINUS, 8, Assoc::Left, Operator::Binary(BinOp::Sub)),
+    (Token::STAR, 9, Assoc::Left, Operator::Binary(BinOp::Mul)),
+    (Token::SLASH, 9, Assoc::Left, Operator::Binary(BinOp::Div)),
+    (Token::SLASHSLASH, 9, Assoc::Left, Operator::Binary(BinOp::FloorDiv)),
+    (Token::PERCENT, 9, Assoc::Left, Operator::Binary(BinOp::Rem)),
+    (Token::STARSTAR, 11, Assoc::Right, Operator::Binary(BinOp::Pow)),
+];
+
+/// 単項マイナスと '~' の優先順位。'*' より強く '**' より弱く結合する
+const UNARY_PRECEDENCE: u8 = 10;
+
+impl Parser {
+    ///
+    /// BinaryExpr = UnaryExpr { BinaryOp UnaryExpr }
+    ///
+    /// 演算子の優先順位と結合性は OPERATOR_TABLE に従う
+    ///
+    pub(crate) fn parse_binary(&mut self) -> Result<Expr, ParseError> { self.parse_precedence(0) }
+
+    /// 現在のトークンが二項演算子であれば、その表の項目を返却する
+    fn operator(&self) -> Option<&'static (Token, u8, Assoc, Operator)> {
+        let token = self.current()?;
+        OPERATOR_TABLE.iter().find(|(t, _, _, _)| *t == token)
+    }
+
+    /// 優先順位が min 以上の演算子だけを読み、それより弱い演算子の手前で止まる
+    fn parse_precedence(&mut self, min: u8) -> Result<Expr, ParseError> {
+        let mut expr = self.parse_unary()?;
+        // 直前に連結できる比較を読んだ場合、その優先順位
+        let mut chain = None;
+        while let Some((_, precedence, assoc, op)) = self.operator() {
+            if *precedence < min {
+                break;
+            }
+            self.fix();
+            let next = if *assoc == Assoc::Right { *precedence } else { precedence + 1 };
+            let rhs = self.parse_precedence(next)?;
+            let span = expr.span.to(rhs.span);
+            let kind = match (op.clone(), expr.kind) {
+                // 同じ優先順位の比較が続く場合は、比較の並びに加える
+                (Operator::Comparison(op), ExprKind::Comparison { lhs, mut rest }) if chain == Some(*precedence) => {
+                    rest.push((op, rhs));
+                    ExprKind::Comparison { lhs, rest }
+                }
+                (op, kind) => {
+                    let lhs = Box::new(Expr { kind, span: expr.span });
+                    match op {
+                        Operator::Binary(op) => ExprKind::Binary { op, lhs, rhs: Box::new(rhs) },
+                        Operator::Comparison(op) => ExprKind::Comparison { lhs, rest: vec![(op, rhs)] },
+                        Operator::Logical(op) => ExprKind::Logical { op, lhs, rhs: Box::new(rhs) },
+                    }
+                }
+            };
+            expr = Expr { kind, span };
+            chain = (*assoc == Assoc::Chain).then_some(*precedence);
+        }
+        Ok(expr)
+    }
+
+    ///
+    /// UnaryExpr = ( '-' | '~' ) UnaryExpr | PostfixExpr
+    ///
+    /// `-2 ** 2` は `-(2 ** 2)` になる。数値リテラルの符号は反転した数値リテラルにする
+    ///
+    fn parse_unary(&mut self) -> Result<Expr, ParseError> {
+        let bitnot = match self.current() {
+            Some(Token::MINUS) => false,
+            Some(Token::TILDE) => true,
+            _ => return self.parse_postfix(),
+        };
+        let start = self.current_span();
+        self.fix();
+        let operand = self.parse_precedence(UNARY_PRECEDENCE + 1)?;
+        let span = start.to(operand.span);
+        if bitnot {
+            return Ok(Expr {
+                kind: ExprKind::BitNot(Box::new(operand)),
+                span,
+            });
+        }
+        let kind = match operand.kind {
+            // -2147483648 のように符号を反転すると i32 に収まる場合がある
+            ExprKind::Number(n) => match n.checked_neg() {
+                Some(n) => ExprKind::Number(n),
+                None => ExprKind::BigNumber(-BigInt::from(n)),
+            },
+            ExprKind::BigNumber(n) => match i32::try_from(-&n) {
+                Ok(i) => ExprKind::Number(i),
+                Err(_) => ExprKind::BigNumber(-n),
+            },
+            ExprKind::Float(n) => ExprKind::Float(-n),
+            ExprKind::Decimal(n) => ExprKind::Decimal(-n),
+            ExprKind::Imaginary(n) => ExprKind::Imaginary(-n),
+            kind => ExprKind::Neg(Box::new(Expr { kind, span: operand.span })),
+        };
+        Ok(Expr { kind, span })
+    }
+}
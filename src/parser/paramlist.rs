@@ -1,6 +1,7 @@
 use crate::parser::Parser;
 
 use crate::enums::Expr;
+use crate::enums::ParseError;
 use crate::enums::Token;
 
 impl Parser {
@@ -11,19 +12,19 @@ impl Parser {
     ///
     /// * None - 引数なし
     ///
-    pub(crate) fn parse_param_list(&mut self) -> Vec<Expr> {
+    pub(crate) fn parse_param_list(&mut self) -> Result<Vec<Expr>, ParseError> {
         let mut param: Vec<Expr> = Vec::new();
 
         // ひとつめのパラメーターを格納する
-        param.push(self.parse_expr());
+        param.push(self.parse_expr()?);
 
         // ふたつめ以降のパラメーターを格納する
         while let Some(Token::COMMA) = self.current() {
-            self.confirm(Token::COMMA);
-            param.push(self.parse_expr());
+            self.confirm(Token::COMMA)?;
+            param.push(self.parse_expr()?);
         }
 
-        param
+        Ok(param)
     }
 
     ///
@@ -33,7 +34,7 @@ impl Parser {
     ///
     /// * None - 引数なし
     ///
-    pub(crate) fn parse_id_list(&mut self) -> Vec<String> {
+    pub(crate) fn parse_id_list(&mut self) -> Result<Vec<String>, ParseError> {
         let mut param: Vec<String> = Vec::new();
 
         // ひとつめのパラメーターを格納する
@@ -43,24 +44,24 @@ impl Parser {
                 self.fix();
             }
             _ => {
-                panic!("ID を期待したが予期せぬ {:?} が出現した", self.current());
+                return Err(self.error(vec!["識別子".to_string()]));
             }
         }
 
         // ふたつめ以降のパラメーターを格納する
         while let Some(Token::COMMA) = self.current() {
-            self.confirm(Token::COMMA);
+            self.confirm(Token::COMMA)?;
             match self.current() {
                 Some(Token::IDENT(s)) => {
                     param.push(s);
                     self.fix();
                 }
                 _ => {
-                    panic!("ID を期待したが予期せぬ {:?} が出現した", self.current());
+                    return Err(self.error(vec!["識別子".to_string()]));
                 }
             }
         }
 
-        param
+        Ok(param)
     }
 }
@@ -1,78 +1,376 @@
 use crate::parser::Parser;
 
+use std::rc::Rc;
+
+use num_bigint::BigInt;
+use rust_decimal::Decimal;
+
 use crate::enums::Expr;
+use crate::enums::ExprKind;
+use crate::enums::ParseError;
+use crate::enums::Statement;
+use crate::enums::StatementKind;
 use crate::enums::Token;
+use crate::unit::Unit;
 
 impl Parser {
     ///
-    /// PrimaryExpr = '(' Expr ')' | NUMBER | ID | FunctionCall
+    /// PostfixExpr = PrimaryExpr { '(' [ ParamList ] ')' | '[' Expr ']' | '[' [ Expr ] '..' [ Expr ] ']' | '.' ID }
+    ///
+    /// 関数として評価される任意の式を呼び出せる。`m.key` は `m["key"]` と同じ意味になる
     ///
-    pub(crate) fn parse_primary(&mut self) -> Expr {
-        return match self.current() {
+    pub(crate) fn parse_postfix(&mut self) -> Result<Expr, ParseError> {
+        let mut expr = self.parse_primary()?;
+        loop {
+            let start = expr.span;
+            let kind = match self.current() {
+                Some(Token::LPAR) => {
+                    self.confirm(Token::LPAR)?;
+                    let args = match self.current() {
+                        Some(Token::RPAR) => Vec::new(),
+                        _ => self.parse_param_list()?,
+                    };
+                    self.confirm(Token::RPAR)?;
+                    ExprKind::FunctionCall {
+                        callee: Box::new(expr),
+                        args,
+                    }
+                }
+                Some(Token::LBRACKET) => self.parse_index(expr)?,
+                Some(Token::DOT) => {
+                    self.fix();
+                    ExprKind::Index {
+                        target: Box::new(expr),
+                        index: Box::new(self.parse_field()?),
+                    }
+                }
+                _ => return Ok(expr),
+            };
+            expr = Expr {
+                span: self.span_from(start),
+                kind,
+            };
+        }
+    }
+
+    /// '.' に続くフィールド名を、キーの文字列として読む
+    pub(crate) fn parse_field(&mut self) -> Result<Expr, ParseError> {
+        match self.current() {
+            Some(Token::IDENT(s)) => {
+                let span = self.current_span();
+                self.fix();
+                Ok(Expr { kind: ExprKind::Str(s), span })
+            }
+            _ => Err(self.error(vec!["フィールド名".to_string()])),
+        }
+    }
+
+    /// 添字 '[' Expr ']' もしくは範囲 '[' [ Expr ] '..' [ Expr ] ']' を読む
+    fn parse_index(&mut self, target: Expr) -> Result<ExprKind, ParseError> {
+        self.confirm(Token::LBRACKET)?;
+        let start = match self.current() {
+            Some(Token::DOTDOT) => None,
+            _ => Some(Box::new(self.parse_expr()?)),
+        };
+
+        let kind = match start {
+            Some(index) if self.current() != Some(Token::DOTDOT) => ExprKind::Index {
+                target: Box::new(target),
+                index,
+            },
+            start => {
+                self.confirm(Token::DOTDOT)?;
+                let end = match self.current() {
+                    Some(Token::RBRACKET) => None,
+                    _ => Some(Box::new(self.parse_expr()?)),
+                };
+                ExprKind::Slice {
+                    target: Box::new(target),
+                    start,
+                    end,
+                }
+            }
+        };
+        self.confirm(Token::RBRACKET)?;
+        Ok(kind)
+    }
+
+    ///
+    /// PrimaryExpr = '(' Expr ')' | '{' Expr '}' | ListExpr | MapExpr | StructExpr | NUMBER [ UNIT ] | FLOAT [ UNIT ] | DECIMAL | IMAGINARY | ID | STR | 'true' | 'false' | '!' PostfixExpr | Lambda | IfExpr
+    ///
+    fn parse_primary(&mut self) -> Result<Expr, ParseError> {
+        let expr = match self.current() {
             Some(Token::LPAR) => self.parse_par(),
-            Some(Token::LBRACE) => self.parse_brace(),
+            // '{' の後が '}' か「キー ':'」であれば辞書、そうでなければ式のまとまり
+            Some(Token::LBRACE) => match (self.peek(1), self.peek(2)) {
+                (Some(Token::RBRACE), _) | (Some(Token::STR(_)) | Some(Token::IDENT(_)), Some(Token::COLON)) => self.parse_map(),
+                _ => self.parse_brace(),
+            },
+            Some(Token::LBRACKET) => self.parse_list(),
             Some(Token::NUMBER(n)) => self.parse_number(n),
-            Some(Token::IDENT(str)) => self.parse_id(str),
+            Some(Token::BIGNUMBER(n)) => self.parse_bignumber(n),
+            Some(Token::FLOAT(n)) => self.parse_float(n),
+            Some(Token::DECIMAL(n)) => self.parse_decimal(n),
+            Some(Token::IMAGINARY(n)) => self.parse_imaginary(n),
+            // 識別子の後が「'{' ID ':'」であれば構造体の値。if x { y = 1 } のようなブロックとは区別する
+            Some(Token::IDENT(str)) => match (self.peek(1), self.peek(2), self.peek(3)) {
+                (Some(Token::LBRACE), Some(Token::IDENT(_)), Some(Token::COLON)) => self.parse_struct(str),
+                _ => self.parse_id(str),
+            },
             Some(Token::STR(str)) => self.parse_str(str),
-            Some(Token::MINUS) => self.parse_negative(),
-            _ => {
-                panic!("PrimaryExpr を判定する際に想定外のトークンがきた");
-            }
-        };
+            Some(Token::TRUE) => self.parse_bool(true),
+            Some(Token::FALSE) => self.parse_bool(false),
+            Some(Token::NOT) => self.parse_not(),
+            Some(Token::PIPE) | Some(Token::OROR) => self.parse_lambda(),
+            Some(Token::IF) => self.parse_if_expr(),
+            _ => Err(self.error(vec!["式".to_string()])),
+        }?;
+
+        // 単位はスキャナが数値リテラルの直後にだけ出力する
+        match self.current() {
+            Some(Token::UNIT(unit)) => self.parse_unit(expr, unit),
+            _ => Ok(expr),
+        }
+    }
+
+    fn parse_unit(&mut self, value: Expr, unit: Unit) -> Result<Expr, ParseError> {
+        self.fix();
+        Ok(Expr {
+            span: self.span_from(value.span),
+            kind: ExprKind::Quantity { value: Box::new(value), unit },
+        })
     }
 
-    fn parse_par(&mut self) -> Expr {
-        self.confirm(Token::LPAR);
-        let result = self.parse_expr();
-        self.confirm(Token::RPAR);
-        result
+    fn parse_par(&mut self) -> Result<Expr, ParseError> {
+        let start = self.current_span();
+        self.confirm(Token::LPAR)?;
+        let result = self.parse_expr()?;
+        self.confirm(Token::RPAR)?;
+        Ok(Expr {
+            kind: result.kind,
+            span: self.span_from(start),
+        })
     }
 
-    fn parse_brace(&mut self) -> Expr {
-        self.confirm(Token::LBRACE);
-        let result = self.parse_expr();
-        self.confirm(Token::RBRACE);
-        result
+    ///
+    /// ListExpr = '[' [ ParamList ] ']'
+    ///
+    fn parse_list(&mut self) -> Result<Expr, ParseError> {
+        let start = self.current_span();
+        self.confirm(Token::LBRACKET)?;
+        let items = match self.current() {
+            Some(Token::RBRACKET) => Vec::new(),
+            _ => self.parse_param_list()?,
+        };
+        self.confirm(Token::RBRACKET)?;
+        Ok(Expr {
+            kind: ExprKind::List(items),
+            span: self.span_from(start),
+        })
     }
 
-    fn parse_number(&mut self, n: i32) -> Expr {
-        self.confirm(Token::NUMBER(n));
-        Expr::Number(n)
+    ///
+    /// MapExpr = '{' [ Entries ] '}'
+    ///
+    fn parse_map(&mut self) -> Result<Expr, ParseError> {
+        let start = self.current_span();
+        let entries = self.parse_entries()?;
+        Ok(Expr {
+            kind: ExprKind::Map(entries),
+            span: self.span_from(start),
+        })
     }
 
-    fn parse_id(&mut self, str: String) -> Expr {
+    ///
+    /// StructExpr = ID '{' Entries '}'
+    ///
+    fn parse_struct(&mut self, name: String) -> Result<Expr, ParseError> {
+        let start = self.current_span();
         self.fix();
+        let fields = self.parse_entries()?;
+        Ok(Expr {
+            kind: ExprKind::Struct { name, fields },
+            span: self.span_from(start),
+        })
+    }
 
-        match self.current() {
-            Some(Token::LPAR) => {
-                self.confirm(Token::LPAR);
-                let args = self.parse_param_list();
-                self.confirm(Token::RPAR);
-                Expr::FunctionCall {
-                    id: str,
-                    args: args,
-                }
+    ///
+    /// '{' [ Entries ] '}' を読む
+    ///
+    /// Entries = Key ':' Expr { ',' Key ':' Expr }
+    /// Key = STR | ID
+    ///
+    fn parse_entries(&mut self) -> Result<Vec<(String, Expr)>, ParseError> {
+        self.confirm(Token::LBRACE)?;
+        let mut entries = Vec::new();
+        while self.current() != Some(Token::RBRACE) {
+            if !entries.is_empty() {
+                self.confirm(Token::COMMA)?;
             }
-            _ => Expr::Var(str),
+            let key = match self.current() {
+                Some(Token::STR(s)) | Some(Token::IDENT(s)) => s,
+                _ => return Err(self.error(vec!["キー".to_string()])),
+            };
+            self.fix();
+            self.confirm(Token::COLON)?;
+            entries.push((key, self.parse_expr()?));
         }
+        self.confirm(Token::RBRACE)?;
+        Ok(entries)
     }
 
-    fn parse_str(&mut self, str: String) -> Expr {
+    fn parse_brace(&mut self) -> Result<Expr, ParseError> {
+        let start = self.current_span();
+        self.confirm(Token::LBRACE)?;
+        let result = self.parse_expr()?;
+        self.confirm(Token::RBRACE)?;
+        Ok(Expr {
+            kind: result.kind,
+            span: self.span_from(start),
+        })
+    }
+
+    fn parse_number(&mut self, n: i32) -> Result<Expr, ParseError> {
+        let start = self.current_span();
+        self.confirm(Token::NUMBER(n))?;
+        Ok(Expr {
+            kind: ExprKind::Number(n),
+            span: start,
+        })
+    }
+
+    fn parse_bignumber(&mut self, n: BigInt) -> Result<Expr, ParseError> {
+        let start = self.current_span();
         self.fix();
-        Expr::Str(str)
+        Ok(Expr {
+            kind: ExprKind::BigNumber(n),
+            span: start,
+        })
     }
 
-    // TODO 負の数考え中
-    fn parse_negative(&mut self) -> Expr {
-        self.confirm(Token::MINUS);
-        match self.current() {
-            Some(Token::NUMBER(n)) => {
+    fn parse_float(&mut self, n: f64) -> Result<Expr, ParseError> {
+        let start = self.current_span();
+        self.fix();
+        Ok(Expr {
+            kind: ExprKind::Float(n),
+            span: start,
+        })
+    }
+
+    fn parse_decimal(&mut self, n: Decimal) -> Result<Expr, ParseError> {
+        let start = self.current_span();
+        self.fix();
+        Ok(Expr {
+            kind: ExprKind::Decimal(n),
+            span: start,
+        })
+    }
+
+    fn parse_imaginary(&mut self, n: f64) -> Result<Expr, ParseError> {
+        let start = self.current_span();
+        self.fix();
+        Ok(Expr {
+            kind: ExprKind::Imaginary(n),
+            span: start,
+        })
+    }
+
+    fn parse_id(&mut self, str: String) -> Result<Expr, ParseError> {
+        let start = self.current_span();
+        self.fix();
+        Ok(Expr {
+            kind: ExprKind::Var(str),
+            span: start,
+        })
+    }
+
+    ///
+    /// Lambda = ( '|' [ ID { ',' ID } ] '|' | '||' ) Expr
+    ///
+    fn parse_lambda(&mut self) -> Result<Expr, ParseError> {
+        let start = self.current_span();
+        // 引数のないラムダ式の '||' は 1 つのトークンになる
+        let arg = if self.current() == Some(Token::OROR) {
+            self.fix();
+            Vec::new()
+        } else {
+            self.confirm(Token::PIPE)?;
+            let arg = match self.current() {
+                Some(Token::PIPE) => Vec::new(),
+                _ => self.parse_id_list()?,
+            };
+            self.confirm(Token::PIPE)?;
+            arg
+        };
+        // 式をそのまま返す関数の本体として保持する
+        let body = self.parse_expr()?;
+        let span = start.to(body.span);
+        let body = Statement {
+            span: body.span,
+            kind: StatementKind::Return { expr: Box::new(body) },
+        };
+        Ok(Expr {
+            span,
+            kind: ExprKind::Lambda { arg, body: Rc::new(body) },
+        })
+    }
+
+    ///
+    /// IfExpr = 'if' Expr '{' Expr '}' [ 'else' ( IfExpr | '{' Expr '}' ) ]
+    ///
+    /// else を省略して条件が偽だった場合は Unit になる
+    ///
+    fn parse_if_expr(&mut self) -> Result<Expr, ParseError> {
+        let start = self.current_span();
+        self.confirm(Token::IF)?;
+        let condition = self.parse_expr()?;
+        let then = self.parse_brace()?;
+        let els = match self.current() {
+            Some(Token::ELSE) => {
                 self.fix();
-                Expr::Number(-1 * n)
-            }
-            _ => {
-                panic!("PrimaryExpr を判定する際に想定外のトークンがきた");
+                match self.current() {
+                    Some(Token::IF) => Some(Box::new(self.parse_if_expr()?)),
+                    _ => Some(Box::new(self.parse_brace()?)),
+                }
             }
-        }
+            _ => None,
+        };
+        Ok(Expr {
+            kind: ExprKind::If {
+                condition: Box::new(condition),
+                then: Box::new(then),
+                els,
+            },
+            span: self.span_from(start),
+        })
+    }
+
+    fn parse_str(&mut self, str: String) -> Result<Expr, ParseError> {
+        let start = self.current_span();
+        self.fix();
+        Ok(Expr {
+            kind: ExprKind::Str(str),
+            span: start,
+        })
+    }
+
+    fn parse_bool(&mut self, b: bool) -> Result<Expr, ParseError> {
+        let start = self.current_span();
+        self.fix();
+        Ok(Expr {
+            kind: ExprKind::Bool(b),
+            span: start,
+        })
+    }
+
+    /// 否定 '!' は後置式だけにかかる（`!f(x)` は `!(f(x))`、`!a == b` は `(!a) == b`）
+    fn parse_not(&mut self) -> Result<Expr, ParseError> {
+        let start = self.current_span();
+        self.confirm(Token::NOT)?;
+        let expr = self.parse_postfix()?;
+        Ok(Expr {
+            span: start.to(expr.span),
+            kind: ExprKind::Not(Box::new(expr)),
+        })
     }
 }
@@ -1,113 +1,345 @@
 use crate::parser::Parser;
 
+use std::rc::Rc;
+
 use crate::enums::Expr;
+use crate::enums::ParseError;
 use crate::enums::Statement;
+use crate::enums::StatementKind;
 use crate::enums::Token;
 
 impl Parser {
     ///
-    /// Statement = IfStatement | AssignStatement | CompoundStatement | ReturnStatement | FunctionDefineStatement
+    /// Statement = IfStatement | AssignStatement | CompoundStatement | ReturnStatement | FunctionDefineStatement | ScopeStatement
     /// CompoundStatement = Statement { ';' Statement }
+    /// IfStatement           = 'if' Expr '{' Statement '}' [ 'else' ( IfStatement | '{' Statement '}' ) ]
     /// FunctionDefineStatement = 'fn' ID ParameterList '{' Statement '}'
+    /// StructDefineStatement = 'struct' ID '{' ID { ',' ID } '}'
     /// ReturnStatement       = 'return' AddExpr
+    /// ScopeStatement        = ( 'global' | 'nonlocal' ) ID { ',' ID }
+    /// WhileStatement        = 'while' Expr '{' Statement '}'
+    /// ForStatement          = 'for' ID 'in' Expr '..' Expr '{' Statement '}'
+    /// BreakStatement        = 'break'
+    /// ContinueStatement     = 'continue'
+    /// PragmaStatement       = 'pragma' ID [ '=' Expr ]
+    ///
+    /// 文の解析に失敗した場合はエラーを記録して次の文から解析を続ける
     ///
     pub(crate) fn parse_state(&mut self) -> Statement {
-        let result = match self.current() {
-            Some(Token::IF) => self.parse_if(),
-            Some(Token::RETURN) => self.parse_return(),
-            Some(Token::FN) => self.parse_functiondefine(),
-            Some(Token::IDENT(s)) => self.parse_ident(s),
-            _ => Statement::Null,
+        let result = match self.parse_single_state() {
+            Ok(st) => st,
+            Err(error) => {
+                self.errors.push(error);
+                let start = self.empty_span();
+                self.synchronize();
+                Statement {
+                    kind: StatementKind::Null,
+                    span: self.span_from(start),
+                }
+            }
         };
 
         // 文の後に ';' が続くようであれば次の文を扱う
         match self.current() {
             Some(Token::SEMICOLON) => self.parse_compound(result),
-            _ => result,
+            Some(Token::RBRACE) if self.depth > 0 => result,
+            None => result,
+            _ => {
+                let mut expected = vec![Token::SEMICOLON.to_string()];
+                if self.depth > 0 {
+                    expected.push(Token::RBRACE.to_string());
+                }
+                self.errors.push(self.error(expected));
+                self.synchronize();
+                match self.current() {
+                    Some(Token::SEMICOLON) => self.parse_compound(result),
+                    _ => result,
+                }
+            }
         }
     }
 
-    pub(crate) fn parse_expr(&mut self) -> Expr { return self.parse_relational(); }
+    fn parse_single_state(&mut self) -> Result<Statement, ParseError> {
+        match self.current() {
+            Some(Token::IF) => self.parse_if(),
+            Some(Token::RETURN) => self.parse_return(),
+            Some(Token::WHILE) => self.parse_while(),
+            Some(Token::FOR) => self.parse_for(),
+            Some(Token::BREAK) => self.parse_loop_jump(StatementKind::Break),
+            Some(Token::CONTINUE) => self.parse_loop_jump(StatementKind::Continue),
+            Some(Token::FN) => self.parse_functiondefine(),
+            Some(Token::STRUCT) => self.parse_structdefine(),
+            Some(Token::GLOBAL) | Some(Token::NONLOCAL) => self.parse_scope(),
+            Some(Token::PRAGMA) => self.parse_pragma(),
+            Some(Token::IDENT(s)) => self.parse_ident(s),
+            Some(Token::SEMICOLON) | Some(Token::RBRACE) | None => Ok(Statement {
+                kind: StatementKind::Null,
+                span: self.empty_span(),
+            }),
+            _ => Err(self.error(vec!["文".to_string()])),
+        }
+    }
+
+    pub(crate) fn parse_expr(&mut self) -> Result<Expr, ParseError> { self.parse_binary() }
+
+    ///
+    /// Block = '{' Statement '}'
+    ///
+    fn parse_block(&mut self) -> Result<Statement, ParseError> {
+        self.confirm(Token::LBRACE)?;
+        self.depth += 1;
+        let state = self.parse_state();
+        self.depth -= 1;
+        self.confirm(Token::RBRACE)?;
+        Ok(state)
+    }
+
+    fn parse_if(&mut self) -> Result<Statement, ParseError> {
+        let start = self.current_span();
+        self.confirm(Token::IF)?;
+        let expr1 = self.parse_expr()?;
 
-    fn parse_if(&mut self) -> Statement {
-        self.confirm(Token::IF);
-        let expr1 = self.parse_expr();
+        let state1 = self.parse_block()?;
 
-        self.confirm(Token::LBRACE);
-        let state1 = self.parse_state();
-        self.confirm(Token::RBRACE);
+        // else は省略でき、else if は入れ子の if 文として扱う
+        let state2 = match self.current() {
+            Some(Token::ELSE) => {
+                self.fix();
+                match self.current() {
+                    Some(Token::IF) => Some(Box::new(self.parse_if()?)),
+                    _ => Some(Box::new(self.parse_block()?)),
+                }
+            }
+            _ => None,
+        };
 
-        self.confirm(Token::ELSE);
-        self.confirm(Token::LBRACE);
-        let state2 = self.parse_state();
-        self.confirm(Token::RBRACE);
+        Ok(Statement {
+            kind: StatementKind::If {
+                condition: Box::new(expr1),
+                then: Box::new(state1),
+                els: state2,
+            },
+            span: self.span_from(start),
+        })
+    }
 
-        Statement::If {
-            condition: Box::new(expr1),
-            then: Box::new(state1),
-            els: Box::new(state2),
-        }
+    /// ループの本体を読む
+    fn parse_loop_body(&mut self) -> Result<Statement, ParseError> {
+        self.loops += 1;
+        let body = self.parse_block();
+        self.loops -= 1;
+        body
+    }
+
+    fn parse_while(&mut self) -> Result<Statement, ParseError> {
+        let start = self.current_span();
+        self.confirm(Token::WHILE)?;
+        let condition = self.parse_expr()?;
+        let body = self.parse_loop_body()?;
+        Ok(Statement {
+            kind: StatementKind::While {
+                condition: Box::new(condition),
+                body: Box::new(body),
+            },
+            span: self.span_from(start),
+        })
     }
 
-    fn parse_return(&mut self) -> Statement {
-        self.confirm(Token::RETURN);
-        Statement::Return {
-            expr: Box::new(self.parse_expr()),
+    fn parse_for(&mut self) -> Result<Statement, ParseError> {
+        let start = self.current_span();
+        self.confirm(Token::FOR)?;
+        let id = match self.current() {
+            Some(Token::IDENT(s)) => s,
+            _ => return Err(self.error(vec!["識別子".to_string()])),
+        };
+        self.fix();
+        self.confirm(Token::IN)?;
+        let from = self.parse_expr()?;
+        self.confirm(Token::DOTDOT)?;
+        let to = self.parse_expr()?;
+        let body = self.parse_loop_body()?;
+        Ok(Statement {
+            kind: StatementKind::For {
+                id,
+                start: Box::new(from),
+                end: Box::new(to),
+                body: Box::new(body),
+            },
+            span: self.span_from(start),
+        })
+    }
+
+    /// break と continue はループの中でのみ使える
+    fn parse_loop_jump(&mut self, kind: StatementKind) -> Result<Statement, ParseError> {
+        if self.loops == 0 {
+            return Err(self.error(vec!["ループの外で使える文".to_string()]));
         }
+        let span = self.current_span();
+        self.fix();
+        Ok(Statement { kind, span })
     }
 
-    fn parse_functiondefine(&mut self) -> Statement {
-        self.confirm(Token::FN);
+    fn parse_return(&mut self) -> Result<Statement, ParseError> {
+        let start = self.current_span();
+        self.confirm(Token::RETURN)?;
+        let expr = self.parse_expr()?;
+        Ok(Statement {
+            span: start.to(expr.span),
+            kind: StatementKind::Return { expr: Box::new(expr) },
+        })
+    }
+
+    fn parse_functiondefine(&mut self) -> Result<Statement, ParseError> {
+        let start = self.current_span();
+        self.confirm(Token::FN)?;
 
         // 関数名
         let id = match self.current() {
             Some(Token::IDENT(s)) => s,
-            _ => panic!(
-                "関数名を表すトークンが来ることを期待したが {:?} が出現した",
-                self.current()
-            ),
+            _ => return Err(self.error(vec!["関数名".to_string()])),
         };
         self.fix();
 
-        // 引数 '(' ID { ',' ID } ')'
-        self.confirm(Token::LPAR);
-        let arg: Vec<String> = self.parse_id_list();
-        self.confirm(Token::RPAR);
+        // 引数 '(' [ ID { ',' ID } ] ')'
+        self.confirm(Token::LPAR)?;
+        let arg: Vec<String> = match self.current() {
+            Some(Token::RPAR) => Vec::new(),
+            _ => self.parse_id_list()?,
+        };
+        self.confirm(Token::RPAR)?;
 
-        // 関数の中身 '{' Statement '}'
-        self.confirm(Token::LBRACE);
-        let state = self.parse_state();
-        self.confirm(Token::RBRACE);
+        // 関数の中身 '{' Statement '}'。外側のループに対して break することはできない
+        let loops = std::mem::replace(&mut self.loops, 0);
+        let state = self.parse_block();
+        self.loops = loops;
+        let state = state?;
 
-        Statement::FunctionDefine {
-            id: id,
-            arg: arg,
-            st: Box::new(state),
-        }
+        Ok(Statement {
+            kind: StatementKind::FunctionDefine {
+                id,
+                arg,
+                st: Rc::new(state),
+            },
+            span: self.span_from(start),
+        })
+    }
+
+    fn parse_structdefine(&mut self) -> Result<Statement, ParseError> {
+        let start = self.current_span();
+        self.confirm(Token::STRUCT)?;
+
+        // 型名
+        let id = match self.current() {
+            Some(Token::IDENT(s)) => s,
+            _ => return Err(self.error(vec!["型名".to_string()])),
+        };
+        self.fix();
+
+        // フィールド名 '{' ID { ',' ID } '}'
+        self.confirm(Token::LBRACE)?;
+        let fields = self.parse_id_list()?;
+        self.confirm(Token::RBRACE)?;
+
+        Ok(Statement {
+            kind: StatementKind::StructDefine { id, fields },
+            span: self.span_from(start),
+        })
+    }
+
+    fn parse_scope(&mut self) -> Result<Statement, ParseError> {
+        let start = self.current_span();
+        let global = self.current() == Some(Token::GLOBAL);
+        self.fix();
+        let ids = self.parse_id_list()?;
+        Ok(Statement {
+            kind: if global { StatementKind::Global { ids } } else { StatementKind::Nonlocal { ids } },
+            span: self.span_from(start),
+        })
     }
 
-    fn parse_ident(&mut self, s: String) -> Statement {
-        match self.next() {
+    fn parse_pragma(&mut self) -> Result<Statement, ParseError> {
+        let start = self.current_span();
+        self.confirm(Token::PRAGMA)?;
+
+        // 設定名
+        let name = match self.current() {
+            Some(Token::IDENT(s)) => s,
+            _ => return Err(self.error(vec!["設定名".to_string()])),
+        };
+        self.fix();
+
+        // 値を省略した場合は true を指定したものとする
+        let value = match self.current() {
             Some(Token::EQ) => {
                 self.fix();
-                self.confirm(Token::EQ);
-                Statement::Assign {
-                    id: s,
-                    e: Box::new(self.parse_expr()),
+                Some(Box::new(self.parse_expr()?))
+            }
+            _ => None,
+        };
+
+        Ok(Statement {
+            kind: StatementKind::Pragma { name, value },
+            span: self.span_from(start),
+        })
+    }
+
+    ///
+    /// AssignStatement = ID { '.' ID | '[' Expr ']' } '=' Expr
+    ///
+    /// 代入先の後に '(' が続く場合は関数呼び出しの文として読み直す
+    ///
+    fn parse_ident(&mut self, s: String) -> Result<Statement, ParseError> {
+        let start = self.current_span();
+        let pos = self.pos;
+        self.fix();
+
+        let mut path = Vec::new();
+        loop {
+            match self.current() {
+                Some(Token::DOT) => {
+                    self.fix();
+                    path.push(self.parse_field()?);
+                }
+                Some(Token::LBRACKET) => {
+                    self.fix();
+                    path.push(self.parse_expr()?);
+                    self.confirm(Token::RBRACKET)?;
                 }
+                _ => break,
             }
-            Some(Token::LPAR) => Statement::FunctionCall {
-                expr: self.parse_expr(),
-            },
-            _ => Statement::Null,
+        }
+
+        match self.current() {
+            Some(Token::EQ) => {
+                self.fix();
+                let e = self.parse_expr()?;
+                Ok(Statement {
+                    span: start.to(e.span),
+                    kind: StatementKind::Assign { id: s, path, e: Box::new(e) },
+                })
+            }
+            Some(Token::LPAR) => {
+                self.pos = pos;
+                let expr = self.parse_expr()?;
+                Ok(Statement {
+                    span: expr.span,
+                    kind: StatementKind::FunctionCall { expr },
+                })
+            }
+            _ => Err(self.error(vec![Token::EQ.to_string(), Token::LPAR.to_string()])),
         }
     }
 
     fn parse_compound(&mut self, st: Statement) -> Statement {
-        self.confirm(Token::SEMICOLON);
-        Statement::CompoundStatement {
-            st1: Box::new(st),
-            st2: Box::new(self.parse_state()),
+        // ';' は呼び出し元で確認済み
+        self.fix();
+        let st2 = self.parse_state();
+        Statement {
+            span: st.span.to(st2.span),
+            kind: StatementKind::CompoundStatement {
+                st1: Box::new(st),
+                st2: Box::new(st2),
+            },
         }
     }
 }
@@ -1,113 +1,413 @@
+use num_bigint::BigInt;
+use rust_decimal::Decimal;
+
+use crate::enums::LexError;
+use crate::enums::LexErrorItem;
+use crate::enums::LexErrorKind;
+use crate::enums::Span;
+use crate::enums::SpannedToken;
 use crate::enums::Token;
+use crate::unit;
 
 struct Scanner {
     input: Vec<char>,
+    // 文字ごとの (バイト位置, 行, 列)。末尾に入力終端の位置を含む
+    positions: Vec<(usize, usize, usize)>,
     pos: usize,
+    // 直前に単位をつけられる数値リテラルを読んだかどうか
+    after_number: bool,
 }
 impl Scanner {
     fn new(s: &str) -> Self {
+        let mut positions = Vec::new();
+        let (mut line, mut col) = (1, 1);
+        for (offset, c) in s.char_indices() {
+            positions.push((offset, line, col));
+            if c == '\n' {
+                line += 1;
+                col = 1;
+            } else {
+                col += 1;
+            }
+        }
+        positions.push((s.len(), line, col));
+
         Scanner {
             input: s.chars().collect(),
+            positions,
             pos: 0,
+            after_number: false,
         }
     }
 
-    /// 次のトークンを読んで返却する
-    fn next(&mut self) -> Option<Token> {
-        while self.pos < self.input.len() {
-            match self.input[self.pos] {
-                ' ' => {
-                    self.pos += 1;
-                }
-                '+' => {
-                    self.pos += 1;
-                    return Some(Token::PLUS);
-                }
-                '-' => {
-                    // TODO 負の数を扱えるようにしたい
-                    self.pos += 1;
-                    return Some(Token::MINUS);
-                }
-                '*' => {
-                    self.pos += 1;
-                    return Some(Token::STAR);
-                }
-                '/' => {
-                    self.pos += 1;
-                    return Some(Token::SLASH);
-                }
-                '<' => {
-                    self.pos += 1;
-                    return Some(Token::LT);
-                }
-                '>' => {
-                    self.pos += 1;
-                    return Some(Token::GT);
-                }
-                '=' => {
-                    self.pos += 1;
-                    return Some(Token::EQ);
+    /// start 文字目から現在位置までの範囲を返却する
+    fn span(&self, start: usize) -> Span {
+        let (offset, line, col) = self.positions[start];
+        Span {
+            start: offset,
+            end: self.positions[self.pos].0,
+            line,
+            col,
+        }
+    }
+
+    /// 1 文字だけのトークンを読んで返却する
+    fn single(&mut self, token: Token) -> Option<Result<SpannedToken, LexErrorItem>> {
+        let start_idx = self.pos;
+        self.pos += 1;
+        Some(Ok(SpannedToken {
+            token,
+            span: self.span(start_idx),
+        }))
+    }
+
+    /// 2 文字のトークンを読んで返却する
+    fn double(&mut self, token: Token) -> Option<Result<SpannedToken, LexErrorItem>> {
+        let start_idx = self.pos;
+        self.pos += 2;
+        Some(Ok(SpannedToken {
+            token,
+            span: self.span(start_idx),
+        }))
+    }
+
+    /// n 文字先を返却する
+    fn peek(&self, n: usize) -> Option<char> { self.input.get(self.pos + n).copied() }
+
+    /// 数字が続く限り読み進める。数字に挟まれた桁区切りの '_' も読む
+    fn digits(&mut self) {
+        while let Some('0'..='9') = self.peek(0) {
+            self.pos += 1;
+            if self.peek(0) == Some('_') && self.peek(1).is_some_and(|c| c.is_ascii_digit()) {
+                self.pos += 1;
+            }
+        }
+    }
+
+    ///
+    /// 基数の接頭辞がついた整数リテラルを読んで返却する
+    ///
+    /// Radix = '0' ( 'x' | 'b' | 'o' ) DIGITS
+    ///
+    /// 数字には '_' を含められる。基数に合わない文字が続く場合はエラーになる
+    ///
+    fn radix(&mut self, radix: u32) -> Option<Result<SpannedToken, LexErrorItem>> {
+        let start_idx = self.pos;
+        self.pos += 2;
+        while self.peek(0).is_some_and(|c| c.is_ascii_alphanumeric() || c == '_') {
+            self.pos += 1;
+        }
+        let text: String = self.input[start_idx..self.pos].iter().collect();
+        let digits: String = text[2..].chars().filter(|c| *c != '_').collect();
+        let n = match BigInt::parse_bytes(digits.as_bytes(), radix) {
+            Some(n) => n,
+            None => {
+                return Some(Err(LexErrorItem {
+                    kind: LexErrorKind::InvalidNumber(text),
+                    span: self.span(start_idx),
+                }))
+            }
+        };
+
+        self.after_number = true;
+        let token = match i32::try_from(&n) {
+            Ok(i) => Token::NUMBER(i),
+            Err(_) => Token::BIGNUMBER(n),
+        };
+        Some(Ok(SpannedToken {
+            token,
+            span: self.span(start_idx),
+        }))
+    }
+
+    /// 現在位置の文字が c だけからなる数値の接尾辞かどうか（`5do` のような識別子の一部とは区別する）
+    fn suffix(&self, c: char) -> bool { self.peek(0) == Some(c) && !self.peek(1).is_some_and(|c| c.is_ascii_alphanumeric() || c == '_') }
+
+    ///
+    /// 数値リテラルの直後の単位を読んで返却する
+    ///
+    /// 空白を挟んで単位表にある単位が続く場合だけ読み進める（`5 km`, `9.8 m/s^2`）
+    /// 単位の中には空白を含められず、`5 m / s` の `/ s` は変数 s による除算になる
+    ///
+    fn unit(&mut self) -> Option<SpannedToken> {
+        let mut pos = self.pos;
+        while let Some(' ') | Some('\t') = self.input.get(pos) {
+            pos += 1;
+        }
+        let start = pos;
+        let is_digit = |c: Option<&char>| c.is_some_and(|c| c.is_ascii_digit());
+
+        // 単位として解釈できる最も長い範囲を探す
+        let mut end = None;
+        let mut result = None;
+        loop {
+            while self.input.get(pos).is_some_and(|c| c.is_ascii_alphabetic()) {
+                pos += 1;
+            }
+            if self.input.get(pos) == Some(&'^') {
+                let sign = (self.input.get(pos + 1) == Some(&'-')) as usize;
+                if is_digit(self.input.get(pos + 1 + sign)) {
+                    pos += 1 + sign;
+                    while is_digit(self.input.get(pos)) {
+                        pos += 1;
+                    }
                 }
-                '(' => {
-                    self.pos += 1;
-                    return Some(Token::LPAR);
+            }
+            let text: String = self.input[start..pos].iter().collect();
+            match unit::parse(&text) {
+                Some(unit) => {
+                    end = Some(pos);
+                    result = Some(unit);
                 }
-                ')' => {
+                None => break,
+            }
+            match self.input.get(pos) {
+                Some('*') | Some('/') => pos += 1,
+                _ => break,
+            }
+        }
+
+        // `5 mx` のように識別子の一部になっている場合は単位として扱わない
+        let end = end?;
+        if self.input.get(end).is_some_and(|c| c.is_ascii_alphanumeric() || *c == '_') {
+            return None;
+        }
+        self.pos = end;
+        Some(SpannedToken {
+            token: Token::UNIT(result?),
+            span: self.span(start),
+        })
+    }
+
+    ///
+    /// 数値リテラルを読んで返却する
+    ///
+    /// Number = Radix | DIGITS [ '.' DIGITS ] [ ( 'e' | 'E' ) [ '+' | '-' ] DIGITS ] [ 'd' | 'i' ]
+    ///
+    /// `0xFF`, `0b1010`, `0o17` はそれぞれ 16 進数、2 進数、8 進数の整数になる
+    /// 数字の間には桁区切りの '_' を書ける（`1_000_000`）
+    /// 小数部か指数部があれば浮動小数点数になる。`1..5` の `..` は小数点として扱わない
+    /// 末尾に `d` が付いていれば、書かれた桁数のままの小数（`12.50d`）になる
+    /// 末尾に `i` が付いていれば虚数（`3i`）になる
+    ///
+    fn number(&mut self) -> Option<Result<SpannedToken, LexErrorItem>> {
+        let radix = match (self.peek(0), self.peek(1)) {
+            (Some('0'), Some('x') | Some('X')) => 16,
+            (Some('0'), Some('b') | Some('B')) => 2,
+            (Some('0'), Some('o') | Some('O')) => 8,
+            _ => 10,
+        };
+        if radix != 10 && self.peek(2).is_some_and(|c| c.is_digit(radix)) {
+            return self.radix(radix);
+        }
+
+        let start_idx = self.pos;
+        let mut float = false;
+        self.digits();
+
+        // 小数部
+        if self.peek(0) == Some('.') && self.peek(1).is_some_and(|c| c.is_ascii_digit()) {
+            self.pos += 1;
+            self.digits();
+            float = true;
+        }
+
+        // 指数部
+        if let Some('e') | Some('E') = self.peek(0) {
+            let sign = matches!(self.peek(1), Some('+') | Some('-')) as usize;
+            if self.peek(1 + sign).is_some_and(|c| c.is_ascii_digit()) {
+                self.pos += 1 + sign;
+                self.digits();
+                float = true;
+            }
+        }
+
+        let text: String = self.input[start_idx..self.pos].iter().filter(|c| **c != '_').collect();
+
+        // 小数
+        if self.suffix('d') {
+            self.pos += 1;
+            let decimal = if text.contains(['e', 'E']) { Decimal::from_scientific(&text) } else { Decimal::from_str_exact(&text) };
+            return Some(match decimal {
+                Ok(d) => Ok(SpannedToken {
+                    token: Token::DECIMAL(d),
+                    span: self.span(start_idx),
+                }),
+                Err(_) => Err(LexErrorItem {
+                    kind: LexErrorKind::InvalidNumber(text + "d"),
+                    span: self.span(start_idx),
+                }),
+            });
+        }
+
+        // 虚数
+        if self.suffix('i') {
+            self.pos += 1;
+            return Some(Ok(SpannedToken {
+                token: Token::IMAGINARY(text.parse().unwrap()),
+                span: self.span(start_idx),
+            }));
+        }
+
+        // i32 に収まらない整数は多倍長整数として扱う
+        self.after_number = true;
+        let token = if float {
+            Token::FLOAT(text.parse().unwrap())
+        } else if let Ok(n) = text.parse() {
+            Token::NUMBER(n)
+        } else {
+            Token::BIGNUMBER(text.parse().unwrap())
+        };
+        Some(Ok(SpannedToken {
+            token,
+            span: self.span(start_idx),
+        }))
+    }
+
+    ///
+    /// エスケープシーケンスを読んで、表す文字を返却する
+    ///
+    /// 現在位置は `\` の次の文字を指している。解釈できない場合は読んだ範囲の文字列を返却する
+    ///
+    fn escape(&mut self) -> Result<char, String> {
+        let start_idx = self.pos - 1;
+        let c = self.peek(0);
+        self.pos = usize::min(self.pos + 1, self.input.len());
+        match c {
+            Some('n') => Ok('\n'),
+            Some('t') => Ok('\t'),
+            Some('"') => Ok('"'),
+            Some('\\') => Ok('\\'),
+            Some('u') if self.peek(0) == Some('{') => {
+                // \u{...} は 16 進数で書いた Unicode のコードポイント
+                self.pos += 1;
+                while self.peek(0).is_some_and(|c| c.is_ascii_hexdigit()) {
                     self.pos += 1;
-                    return Some(Token::RPAR);
                 }
-                '{' => {
+                let closed = self.peek(0) == Some('}');
+                if closed {
                     self.pos += 1;
-                    return Some(Token::LBRACE);
                 }
-                '}' => {
-                    self.pos += 1;
-                    return Some(Token::RBRACE);
+                let text: String = self.input[start_idx..self.pos].iter().collect();
+                let digits = &text[3..text.len() - closed as usize];
+                match u32::from_str_radix(digits, 16).ok().and_then(char::from_u32) {
+                    Some(c) if closed => Ok(c),
+                    _ => Err(text),
                 }
-                ',' => {
+            }
+            _ => Err(self.input[start_idx..self.pos].iter().collect()),
+        }
+    }
+
+    ///
+    /// 文字列リテラルを読んで返却する
+    ///
+    /// Str = '"' { CHAR | '\\' ( 'n' | 't' | '"' | '\\' | 'u{' HEX '}' ) } '"'
+    ///
+    /// 解釈できないエスケープシーケンスがあっても文字列の終わりまで読み進める
+    ///
+    fn string(&mut self) -> Option<Result<SpannedToken, LexErrorItem>> {
+        let quote_idx = self.pos;
+        self.pos += 1;
+
+        // 次の " が来るまで読む
+        let mut str = String::new();
+        let mut error = None;
+        while self.pos < self.input.len() {
+            match self.input[self.pos] {
+                '"' => break,
+                '\\' => {
+                    let escape_idx = self.pos;
                     self.pos += 1;
-                    return Some(Token::COMMA);
+                    match self.escape() {
+                        Ok(c) => str.push(c),
+                        Err(text) => {
+                            error.get_or_insert(LexErrorItem {
+                                kind: LexErrorKind::InvalidEscape(text),
+                                span: self.span(escape_idx),
+                            });
+                        }
+                    }
                 }
-                ';' => {
+                c => {
+                    str.push(c);
                     self.pos += 1;
-                    return Some(Token::SEMICOLON);
                 }
-                '"' => {
-                    self.pos += 1;
-                    let start_idx = self.pos;
+            }
+        }
 
-                    // 次の " が来るまで読む
-                    while self.pos < self.input.len() {
-                        match self.input[self.pos] {
-                            '"' => break,
-                            _ => self.pos += 1,
-                        }
-                    }
-                    let _str: String = self.input[start_idx..self.pos]
-                        .iter()
-                        .collect::<String>()
-                        .parse()
-                        .unwrap();
+        // 閉じる " がないまま入力が終わった場合は開始位置の " を指し示す
+        if self.pos >= self.input.len() {
+            let (offset, line, col) = self.positions[quote_idx];
+            return Some(Err(LexErrorItem {
+                kind: LexErrorKind::UnterminatedString,
+                span: Span {
+                    start: offset,
+                    end: self.positions[quote_idx + 1].0,
+                    line,
+                    col,
+                },
+            }));
+        }
 
+        self.pos += 1;
+        Some(match error {
+            Some(error) => Err(error),
+            None => Ok(SpannedToken {
+                token: Token::STR(str),
+                span: self.span(quote_idx),
+            }),
+        })
+    }
+
+    /// 次のトークンを読んで返却する
+    fn next(&mut self) -> Option<Result<SpannedToken, LexErrorItem>> {
+        if std::mem::take(&mut self.after_number) {
+            if let Some(token) = self.unit() {
+                return Some(Ok(token));
+            }
+        }
+        while self.pos < self.input.len() {
+            match self.input[self.pos] {
+                ' ' | '\t' | '\r' | '\n' => {
                     self.pos += 1;
-                    return Some(Token::STR(_str));
-                }
-                '0'..='9' => {
-                    let start_idx = self.pos;
-                    // 数字が続く限り次を読む
-                    while self.pos < self.input.len() {
-                        match self.input[self.pos] {
-                            '0'..='9' => self.pos += 1,
-                            _ => break,
-                        }
-                    }
-                    let num: i32 = self.input[start_idx..self.pos]
-                        .iter()
-                        .collect::<String>()
-                        .parse()
-                        .unwrap();
-                    return Some(Token::NUMBER(num));
                 }
+                '+' => return self.single(Token::PLUS),
+                '-' => return self.single(Token::MINUS),
+                '*' if self.peek(1) == Some('*') => return self.double(Token::STARSTAR),
+                '*' => return self.single(Token::STAR),
+                '/' if self.peek(1) == Some('/') => return self.double(Token::SLASHSLASH),
+                '/' => return self.single(Token::SLASH),
+                '%' => return self.single(Token::PERCENT),
+                '<' if self.peek(1) == Some('=') => return self.double(Token::LE),
+                '<' if self.peek(1) == Some('<') => return self.double(Token::SHL),
+                '<' => return self.single(Token::LT),
+                '>' if self.peek(1) == Some('=') => return self.double(Token::GE),
+                '>' if self.peek(1) == Some('>') => return self.double(Token::SHR),
+                '>' => return self.single(Token::GT),
+                '=' if self.peek(1) == Some('=') => return self.double(Token::EQEQ),
+                '=' => return self.single(Token::EQ),
+                '!' if self.peek(1) == Some('=') => return self.double(Token::NOTEQ),
+                '!' => return self.single(Token::NOT),
+                '&' if self.peek(1) == Some('&') => return self.double(Token::ANDAND),
+                '&' => return self.single(Token::AMP),
+                '^' => return self.single(Token::CARET),
+                '~' => return self.single(Token::TILDE),
+                '|' if self.peek(1) == Some('|') => return self.double(Token::OROR),
+                '(' => return self.single(Token::LPAR),
+                ')' => return self.single(Token::RPAR),
+                '{' => return self.single(Token::LBRACE),
+                '}' => return self.single(Token::RBRACE),
+                '[' => return self.single(Token::LBRACKET),
+                ']' => return self.single(Token::RBRACKET),
+                ',' => return self.single(Token::COMMA),
+                ';' => return self.single(Token::SEMICOLON),
+                '|' => return self.single(Token::PIPE),
+                '.' if self.peek(1) == Some('.') => return self.double(Token::DOTDOT),
+                '.' => return self.single(Token::DOT),
+                ':' => return self.single(Token::COLON),
+                '"' => return self.string(),
+                '0'..='9' => return self.number(),
 
                 'a'..='z' | 'A'..='Z' => {
                     let start_idx = self.pos;
@@ -121,38 +421,68 @@ impl Scanner {
                             _ => break,
                         }
                     }
-                    let _word: String = self.input[start_idx..self.pos]
-                        .iter()
-                        .collect::<String>()
-                        .parse()
-                        .unwrap();
+                    let _word: String = self.input[start_idx..self.pos].iter().collect();
 
                     // 予約語
-                    return match &*_word {
-                        "if" => Some(Token::IF),
-                        "else" => Some(Token::ELSE),
-                        "return" => Some(Token::RETURN),
-                        "fn" => Some(Token::FN),
-                        _ => Some(Token::IDENT(_word)),
+                    let token = match &*_word {
+                        "if" => Token::IF,
+                        "else" => Token::ELSE,
+                        "return" => Token::RETURN,
+                        "fn" => Token::FN,
+                        "struct" => Token::STRUCT,
+                        "global" => Token::GLOBAL,
+                        "nonlocal" => Token::NONLOCAL,
+                        "while" => Token::WHILE,
+                        "for" => Token::FOR,
+                        "in" => Token::IN,
+                        "break" => Token::BREAK,
+                        "continue" => Token::CONTINUE,
+                        "pragma" => Token::PRAGMA,
+                        "true" => Token::TRUE,
+                        "false" => Token::FALSE,
+                        _ => Token::IDENT(_word),
                     };
+                    return Some(Ok(SpannedToken {
+                        token,
+                        span: self.span(start_idx),
+                    }));
                 }
 
-                _ => {
-                    panic!("予期しない文字 {} を検知した", self.input[self.pos]);
+                c => {
+                    // 読み飛ばして続きをスキャンできるようにする
+                    let start_idx = self.pos;
+                    self.pos += 1;
+                    return Some(Err(LexErrorItem {
+                        kind: LexErrorKind::UnexpectedChar(c),
+                        span: self.span(start_idx),
+                    }));
                 }
             }
         }
-        return None;
+        None
     }
 }
 
-pub fn scanner(s: &str) -> Vec<Token> {
+///
+/// 文字列をトークン列に変換する
+///
+/// 想定外の文字があってもスキャンを続け、検出したエラーをすべて返却する
+///
+pub fn scanner(s: &str) -> Result<Vec<SpannedToken>, LexError> {
     let mut result = Vec::new();
+    let mut errors = Vec::new();
 
     let mut scanner = Scanner::new(s);
     while let Some(token) = scanner.next() {
-        result.push(token);
+        match token {
+            Ok(token) => result.push(token),
+            Err(error) => errors.push(error),
+        }
     }
 
-    result
+    if errors.is_empty() {
+        Ok(result)
+    } else {
+        Err(LexError { items: errors })
+    }
 }
@@ -1,174 +1,1371 @@
 #[cfg(test)]
+#[allow(clippy::module_inception)]
 mod tests {
     use crate::scanner;
     use crate::parser;
     use crate::evaluator;
+    use crate::evaluator::builtin;
+    use crate::diagnostic;
+    use crate::env::Env;
 
-    use crate::enums::Env;
+    use crate::enums::ExprKind;
     use crate::enums::FunctionTable;
+    use crate::enums::LexErrorKind;
+    use crate::enums::Span;
+    use crate::enums::StatementKind;
+    use crate::enums::Syntax;
+    use crate::enums::Token;
     use crate::enums::Value;
 
+    use num_bigint::BigInt;
+
     #[test]
     fn test_assign() {
         let str = "x = 123";
-        let mut env = Env::new();
+        let env = Env::new();
         let mut ft = FunctionTable::new();
 
         // 実行後に x = 123 が代入されていること
-        evaluator::eval(parser::parser(scanner::scanner(str)), &mut env, &mut ft);
-        assert_eq!(env["x"], Value::Int(123));
+        evaluator::eval(parser::parser(scanner::scanner(str).unwrap()).unwrap(), &env, &mut ft).unwrap();
+        assert_eq!(env.get("x").unwrap(), Value::Int(123));
     }
 
     #[test]
     fn test_negative() {
         let str = "x = -1";
-        let mut env = Env::new();
+        let env = Env::new();
         let mut ft = FunctionTable::new();
 
         // 実行後に x = -1 が代入されていること
-        evaluator::eval(parser::parser(scanner::scanner(str)), &mut env, &mut ft);
-        assert_eq!(env["x"], Value::Int(-1));
+        evaluator::eval(parser::parser(scanner::scanner(str).unwrap()).unwrap(), &env, &mut ft).unwrap();
+        assert_eq!(env.get("x").unwrap(), Value::Int(-1));
     }
 
     #[test]
     fn test_if() {
         let str = "if 0 { x = 2 } else { x = 3 }";
-        let mut env = Env::new();
+        let env = Env::new();
         let mut ft = FunctionTable::new();
 
         // 実行後に x = 3 が代入されていること
-        evaluator::eval(parser::parser(scanner::scanner(str)), &mut env, &mut ft);
-        assert_eq!(env["x"], Value::Int(3));
+        evaluator::eval(parser::parser(scanner::scanner(str).unwrap()).unwrap(), &env, &mut ft).unwrap();
+        assert_eq!(env.get("x").unwrap(), Value::Int(3));
     }
 
     #[test]
     fn test_addition() {
         let str = "x = 1 + 2 + 3";
-        let mut env = Env::new();
+        let env = Env::new();
         let mut ft = FunctionTable::new();
 
         // 実行後に x = 6 が代入されていること
-        evaluator::eval(parser::parser(scanner::scanner(str)), &mut env, &mut ft);
-        assert_eq!(env["x"], Value::Int(6));
+        evaluator::eval(parser::parser(scanner::scanner(str).unwrap()).unwrap(), &env, &mut ft).unwrap();
+        assert_eq!(env.get("x").unwrap(), Value::Int(6));
     }
 
     #[test]
     fn test_subtraction() {
         let str = "x = 1 - 2 - 3";
-        let mut env = Env::new();
+        let env = Env::new();
         let mut ft = FunctionTable::new();
 
         // 実行後に x = -4 が代入されていること
-        evaluator::eval(parser::parser(scanner::scanner(str)), &mut env, &mut ft);
-        assert_eq!(env["x"], Value::Int(-4));
+        evaluator::eval(parser::parser(scanner::scanner(str).unwrap()).unwrap(), &env, &mut ft).unwrap();
+        assert_eq!(env.get("x").unwrap(), Value::Int(-4));
     }
 
     #[test]
     fn test_multiplication() {
         let str = "x = 1 * 2 * 3";
-        let mut env = Env::new();
+        let env = Env::new();
         let mut ft = FunctionTable::new();
 
         // 実行後に x = 6 が代入されていること
-        evaluator::eval(parser::parser(scanner::scanner(str)), &mut env, &mut ft);
-        assert_eq!(env["x"], Value::Int(6));
+        evaluator::eval(parser::parser(scanner::scanner(str).unwrap()).unwrap(), &env, &mut ft).unwrap();
+        assert_eq!(env.get("x").unwrap(), Value::Int(6));
     }
 
     #[test]
     fn test_division() {
         let str = "x = 4 / 2 / 2";
-        let mut env = Env::new();
+        let env = Env::new();
         let mut ft = FunctionTable::new();
 
         // 実行後に x = 1 が代入されていること
-        evaluator::eval(parser::parser(scanner::scanner(str)), &mut env, &mut ft);
-        assert_eq!(env["x"], Value::Int(1));
+        evaluator::eval(parser::parser(scanner::scanner(str).unwrap()).unwrap(), &env, &mut ft).unwrap();
+        assert_eq!(env.get("x").unwrap(), Value::Int(1));
     }
 
     #[test]
     fn test_zero_division() {
         let str = "x = 4 / 0";
-        let mut env = Env::new();
+        let env = Env::new();
         let mut ft = FunctionTable::new();
 
         // 実行後に0で除算を試みた時点でエラーで止まること
-        evaluator::eval(parser::parser(scanner::scanner(str)), &mut env, &mut ft);
+        let error = evaluator::eval(parser::parser(scanner::scanner(str).unwrap()).unwrap(), &env, &mut ft).unwrap_err();
+        assert_eq!(error.span, Span { start: 4, end: 9, line: 1, col: 5 });
+        assert_eq!(env.get("x"), None);
     }
 
     #[test]
     fn test_parenthesis() {
         let str = "x = 2 * (3 + 4) ";
-        let mut env = Env::new();
+        let env = Env::new();
         let mut ft = FunctionTable::new();
 
         // 実行後に x = 14 が代入されていること
-        evaluator::eval(parser::parser(scanner::scanner(str)), &mut env, &mut ft);
-        assert_eq!(env["x"], Value::Int(14));
+        evaluator::eval(parser::parser(scanner::scanner(str).unwrap()).unwrap(), &env, &mut ft).unwrap();
+        assert_eq!(env.get("x").unwrap(), Value::Int(14));
     }
 
     #[test]
     fn test_compound_statement() {
         let str = "if 0 { x = 0 } else { x = 1 } ; if x { x = 3 } else { x = 4 }";
-        let mut env = Env::new();
+        let env = Env::new();
         let mut ft = FunctionTable::new();
 
         // 実行後に x = 3 が代入されていること
-        evaluator::eval(parser::parser(scanner::scanner(str)), &mut env, &mut ft);
-        assert_eq!(env["x"], Value::Int(3));
+        evaluator::eval(parser::parser(scanner::scanner(str).unwrap()).unwrap(), &env, &mut ft).unwrap();
+        assert_eq!(env.get("x").unwrap(), Value::Int(3));
     }
 
     #[test]
     fn test_function_define() {
         let str = "fn test(i) { return i + 1 }; x = (test(2) + 3)";
-        let mut env = Env::new();
+        let env = Env::new();
         let mut ft = FunctionTable::new();
 
         // 実行後に x = 6 が代入されていること
-        evaluator::eval(parser::parser(scanner::scanner(str)), &mut env, &mut ft);
-        assert_eq!(env["x"], Value::Int(6));
+        evaluator::eval(parser::parser(scanner::scanner(str).unwrap()).unwrap(), &env, &mut ft).unwrap();
+        assert_eq!(env.get("x").unwrap(), Value::Int(6));
     }
 
     #[test]
     fn test_function_call() {
         let str = "fn fib(n) { if ( n < 3 ) { return 1 } else { return fib( n - 1 ) + fib( n - 2 ) } }; x = fib(22)";
-        let mut env = Env::new();
+        let env = Env::new();
         let mut ft = FunctionTable::new();
 
         // 実行後に x = 17711 が代入されていること
-        evaluator::eval(parser::parser(scanner::scanner(str)), &mut env, &mut ft);
-        assert_eq!(env["x"], Value::Int(17711));
+        evaluator::eval(parser::parser(scanner::scanner(str).unwrap()).unwrap(), &env, &mut ft).unwrap();
+        assert_eq!(env.get("x").unwrap(), Value::Int(17711));
     }
 
     #[test]
     fn test_builtin_function_call() {
         let str = "x = 1 + 2 + 3; print_int(x)";
-        let mut env = Env::new();
+        let env = Env::new();
         let mut ft = FunctionTable::new();
 
         // 実行後に x = 6 が代入されていること
-        evaluator::eval(parser::parser(scanner::scanner(str)), &mut env, &mut ft);
-        assert_eq!(env["x"], Value::Int(6));
+        evaluator::eval(parser::parser(scanner::scanner(str).unwrap()).unwrap(), &env, &mut ft).unwrap();
+        assert_eq!(env.get("x").unwrap(), Value::Int(6));
     }
 
     #[test]
     fn test_str_value() {
         let str = "x=\"Hello\"; print_str(x)";
-        let mut env = Env::new();
+        let env = Env::new();
         let mut ft = FunctionTable::new();
 
         // 実行後に x = Hello が代入されていること
-        evaluator::eval(parser::parser(scanner::scanner(str)), &mut env, &mut ft);
-        assert_eq!(env["x"], Value::String("Hello".to_string()));
+        evaluator::eval(parser::parser(scanner::scanner(str).unwrap()).unwrap(), &env, &mut ft).unwrap();
+        assert_eq!(env.get("x").unwrap(), Value::String("Hello".to_string()));
     }
 
     #[test]
     fn test_str_add() {
         let str = "x = \"abc\"; y = \"def\"; z = x + y";
-        let mut env = Env::new();
+        let env = Env::new();
         let mut ft = FunctionTable::new();
 
         // 実行後に z = abcdef が代入されていること
-        evaluator::eval(parser::parser(scanner::scanner(str)), &mut env, &mut ft);
-        assert_eq!(env["z"], Value::String("abcdef".to_string()));
+        evaluator::eval(parser::parser(scanner::scanner(str).unwrap()).unwrap(), &env, &mut ft).unwrap();
+        assert_eq!(env.get("z").unwrap(), Value::String("abcdef".to_string()));
+    }
+
+    #[test]
+    fn test_token_span() {
+        let str = "x = 1;\n  y = \"あ\"";
+
+        // 各トークンがバイト位置と行・列を保持していること
+        let tokens = scanner::scanner(str).unwrap();
+        assert_eq!(tokens[0].token, Token::IDENT("x".to_string()));
+        assert_eq!(tokens[0].span, Span { start: 0, end: 1, line: 1, col: 1 });
+        assert_eq!(tokens[4].token, Token::IDENT("y".to_string()));
+        assert_eq!(tokens[4].span, Span { start: 9, end: 10, line: 2, col: 3 });
+        assert_eq!(tokens[6].token, Token::STR("あ".to_string()));
+        assert_eq!(tokens[6].span, Span { start: 13, end: 18, line: 2, col: 7 });
+    }
+
+    #[test]
+    fn test_lex_error() {
+        let str = "x = 3 $ 2;\ny = 1@5";
+
+        // 想定外の文字がすべて位置つきで報告されること
+        let error = scanner::scanner(str).unwrap_err();
+        assert_eq!(error.items.len(), 2);
+        assert_eq!(error.items[0].kind, LexErrorKind::UnexpectedChar('$'));
+        assert_eq!(error.items[0].span, Span { start: 6, end: 7, line: 1, col: 7 });
+        assert_eq!(error.items[1].kind, LexErrorKind::UnexpectedChar('@'));
+        assert_eq!(error.items[1].span, Span { start: 16, end: 17, line: 2, col: 6 });
+    }
+
+    #[test]
+    fn test_unterminated_string() {
+        let str = "x = 1;\ny = \"abc; print(y)";
+
+        // 閉じられていない文字列は開始位置の \" を指してスキャンエラーになること
+        let error = scanner::scanner(str).unwrap_err();
+        assert_eq!(error.items.len(), 1);
+        assert_eq!(error.items[0].kind, LexErrorKind::UnterminatedString);
+        assert_eq!(error.items[0].span, Span { start: 11, end: 12, line: 2, col: 5 });
+        assert_eq!(error.to_string(), "2行5列目（11バイト目）: 文字列が閉じられていない\n");
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        let str = "x = 1 +; y = (2 * 3; if x { z = } else { z = 1 }; w = 4";
+
+        // 3 箇所の誤りがすべて報告されること
+        let errors = parser::parser(scanner::scanner(str).unwrap()).unwrap_err();
+        assert_eq!(errors.len(), 3);
+        assert_eq!(errors[0].expected, vec!["式".to_string()]);
+        assert_eq!(errors[0].found, Some(Token::SEMICOLON));
+        assert_eq!(errors[0].span, Span { start: 7, end: 8, line: 1, col: 8 });
+        assert_eq!(errors[1].expected, vec![Token::RPAR.to_string()]);
+        assert_eq!(errors[1].found, Some(Token::SEMICOLON));
+        assert_eq!(errors[2].found, Some(Token::RBRACE));
+        assert_eq!(errors[2].span.col, 33);
+    }
+
+    #[test]
+    fn test_parse_error_at_end() {
+        let str = "x = (1 + 2";
+
+        // 入力の終わりで ')' が足りないことが報告されること
+        let errors = parser::parser(scanner::scanner(str).unwrap()).unwrap_err();
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].expected, vec![Token::RPAR.to_string()]);
+        assert_eq!(errors[0].found, None);
+        assert_eq!(errors[0].span, Span { start: 10, end: 10, line: 1, col: 11 });
+    }
+
+    #[test]
+    fn test_ast_span() {
+        let str = "x = 1;\ny = 2 * (x + 3)";
+
+        // 文と式がソース上の範囲を保持していること
+        let Syntax::Statement(st) = parser::parser(scanner::scanner(str).unwrap()).unwrap();
+        let StatementKind::CompoundStatement { st2, .. } = st.kind else { panic!() };
+        assert_eq!(st2.span, Span { start: 7, end: 22, line: 2, col: 1 });
+        let StatementKind::Assign { e, .. } = st2.kind else { panic!() };
+        assert_eq!(e.span, Span { start: 11, end: 22, line: 2, col: 5 });
+        let ExprKind::Binary { rhs, .. } = e.kind else { panic!() };
+        assert_eq!(rhs.span, Span { start: 15, end: 22, line: 2, col: 9 });
+    }
+
+    #[test]
+    fn test_render_runtime_error() {
+        let str = "x = 1;\ny = x + \"a\"";
+        let env = Env::new();
+        let mut ft = FunctionTable::new();
+
+        // 実行時エラーが該当行と下線つきで表示されること
+        let error = evaluator::eval(parser::parser(scanner::scanner(str).unwrap()).unwrap(), &env, &mut ft).unwrap_err();
+        assert_eq!(diagnostic::render(str, &error), "エラー: 加算処理が未定義\n  --> 2行5列目\n  |\n2 | y = x + \"a\"\n  |     ^^^^^^^\n");
+    }
+
+    #[test]
+    fn test_render_wide_char() {
+        let str = "x = \"あい\" $ 2";
+
+        // 全角文字を含む行でも下線の位置がずれないこと
+        let error = scanner::scanner(str).unwrap_err();
+        assert_eq!(diagnostic::render(str, &error.items[0]), "エラー: 予期しない文字 '$' を検知した\n  --> 1行10列目\n  |\n1 | x = \"あい\" $ 2\n  |            ^\n");
+    }
+
+    #[test]
+    fn test_early_return() {
+        let str = "fn f(x) { return 1; return 2 }; y = f(0)";
+        let env = Env::new();
+        let mut ft = FunctionTable::new();
+
+        // 最初の return で関数を抜けること
+        evaluator::eval(parser::parser(scanner::scanner(str).unwrap()).unwrap(), &env, &mut ft).unwrap();
+        assert_eq!(env.get("y").unwrap(), Value::Int(1));
+    }
+
+    #[test]
+    fn test_return_in_if() {
+        let str = "fn f(x) { if x { return 1 } else { y = 0 }; return y + 1 / 0 }; z = f(1)";
+        let env = Env::new();
+        let mut ft = FunctionTable::new();
+
+        // if の中の return で関数を抜け、後続の文が実行されないこと
+        evaluator::eval(parser::parser(scanner::scanner(str).unwrap()).unwrap(), &env, &mut ft).unwrap();
+        assert_eq!(env.get("z").unwrap(), Value::Int(1));
+    }
+
+    #[test]
+    fn test_function_without_return() {
+        let str = "fn f(x) { y = x }; z = f(1)";
+        let env = Env::new();
+        let mut ft = FunctionTable::new();
+
+        // return しない関数は Unit を返すこと
+        evaluator::eval(parser::parser(scanner::scanner(str).unwrap()).unwrap(), &env, &mut ft).unwrap();
+        assert_eq!(env.get("z").unwrap(), Value::Unit);
+    }
+
+    #[test]
+    fn test_global_read() {
+        let str = "y = 10; fn f(x) { return x + y }; z = f(1)";
+        let env = Env::new();
+        let mut ft = FunctionTable::new();
+
+        // 関数の中から大域変数を参照できること
+        evaluator::eval(parser::parser(scanner::scanner(str).unwrap()).unwrap(), &env, &mut ft).unwrap();
+        assert_eq!(env.get("z").unwrap(), Value::Int(11));
+    }
+
+    #[test]
+    fn test_local_by_default() {
+        let str = "y = 10; fn f(x) { y = x; return y }; z = f(1)";
+        let env = Env::new();
+        let mut ft = FunctionTable::new();
+
+        // 関数の中での代入はローカル変数になり、大域変数を書き換えないこと
+        evaluator::eval(parser::parser(scanner::scanner(str).unwrap()).unwrap(), &env, &mut ft).unwrap();
+        assert_eq!(env.get("z").unwrap(), Value::Int(1));
+        assert_eq!(env.get("y").unwrap(), Value::Int(10));
+    }
+
+    #[test]
+    fn test_global_write() {
+        let str = "y = 10; fn f(x) { global y, w; y = x; w = x + 1 }; f(1)";
+        let env = Env::new();
+        let mut ft = FunctionTable::new();
+
+        // global 宣言した変数への代入は大域変数に反映されること
+        evaluator::eval(parser::parser(scanner::scanner(str).unwrap()).unwrap(), &env, &mut ft).unwrap();
+        assert_eq!(env.get("y").unwrap(), Value::Int(1));
+        assert_eq!(env.get("w").unwrap(), Value::Int(2));
+    }
+
+    #[test]
+    fn test_closure() {
+        let str = "fn outer(x) { n = x; fn inc(d) { nonlocal n; n = n + d }; inc(1); inc(2); return n }; z = outer(10)";
+        let env = Env::new();
+        let mut ft = FunctionTable::new();
+
+        // 入れ子の関数が定義時の環境を参照・更新できること
+        evaluator::eval(parser::parser(scanner::scanner(str).unwrap()).unwrap(), &env, &mut ft).unwrap();
+        assert_eq!(env.get("z").unwrap(), Value::Int(13));
+        assert_eq!(env.get("n"), None);
+    }
+
+    #[test]
+    fn test_nonlocal_not_found() {
+        let str = "fn f(x) { nonlocal n; n = x }; f(1)";
+        let env = Env::new();
+        let mut ft = FunctionTable::new();
+
+        // 外側に存在しない変数への nonlocal 代入はエラーになること
+        let result = evaluator::eval(parser::parser(scanner::scanner(str).unwrap()).unwrap(), &env, &mut ft);
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_function_value() {
+        let str = "fn apply(f, x) { return f(x) }; fn double(x) { return x * 2 }; g = double; y = apply(g, 3); z = apply(|x| x + 1, 3)";
+        let env = Env::new();
+        let mut ft = FunctionTable::new();
+
+        // 関数を変数に格納し、引数として渡せること
+        evaluator::eval(parser::parser(scanner::scanner(str).unwrap()).unwrap(), &env, &mut ft).unwrap();
+        assert_eq!(env.get("y").unwrap(), Value::Int(6));
+        assert_eq!(env.get("z").unwrap(), Value::Int(4));
+    }
+
+    #[test]
+    fn test_lambda_closure() {
+        let str = "fn make_adder(n) { return |x| x + n }; add2 = make_adder(2); y = add2(3) + make_adder(10)(1); k = || 7; z = k()";
+        let env = Env::new();
+        let mut ft = FunctionTable::new();
+
+        // ラムダ式が定義時の環境を保持し、返り値として返せること
+        evaluator::eval(parser::parser(scanner::scanner(str).unwrap()).unwrap(), &env, &mut ft).unwrap();
+        assert_eq!(env.get("y").unwrap(), Value::Int(16));
+        assert_eq!(env.get("z").unwrap(), Value::Int(7));
+    }
+
+    #[test]
+    fn test_nested_function_scope() {
+        let str = "fn make(n) { fn f(k) { if k { return f(k - 1) } else { return n } }; return f }; a = make(1); b = make(2); x = a(3); y = b(3); fn outer() { fn inner() { return 1 }; return inner() }; z = outer()";
+        let env = Env::new();
+        let mut ft = FunctionTable::new();
+
+        // 入れ子の関数は呼び出しごとに別の関数として定義されること
+        evaluator::eval(parser::parser(scanner::scanner(str).unwrap()).unwrap(), &env, &mut ft).unwrap();
+        assert_eq!(env.get("x").unwrap(), Value::Int(1));
+        assert_eq!(env.get("y").unwrap(), Value::Int(2));
+        assert_eq!(env.get("z").unwrap(), Value::Int(1));
+
+        // 入れ子の関数は外側の関数の外からは参照できないこと
+        let error = evaluator::eval(parser::parser(scanner::scanner("w = inner()").unwrap()).unwrap(), &env, &mut ft).unwrap_err();
+        assert_eq!(error.message, "変数 \"inner\" が登録されていない");
+    }
+
+    #[test]
+    fn test_recursion_depth() {
+        // 実行用のスレッドと同じく、大きなスタックを持つスレッドで実行する
+        let runner = std::thread::Builder::new().stack_size(512 * 1024 * 1024).spawn(|| {
+            let str = "fn f(n) { if n { return f(n - 1) + 1 } else { return 0 } }; x = f(5000)";
+            let env = Env::new();
+            let mut ft = FunctionTable::new();
+
+            // 深い再帰も上限までは実行できること
+            evaluator::eval(parser::parser(scanner::scanner(str).unwrap()).unwrap(), &env, &mut ft).unwrap();
+            assert_eq!(env.get("x").unwrap(), Value::Int(5000));
+
+            // 上限を超える再帰はスタックを使い果たす前にエラーになること
+            let error = evaluator::eval(parser::parser(scanner::scanner("y = f(20000)").unwrap()).unwrap(), &env, &mut ft).unwrap_err();
+            assert_eq!(error.message, "関数呼び出しの深さが上限の 10000 を超えた");
+
+            // エラーの後も、呼び出しの深さは元に戻っていること
+            evaluator::eval(parser::parser(scanner::scanner("z = f(9000)").unwrap()).unwrap(), &env, &mut ft).unwrap();
+            assert_eq!(env.get("z").unwrap(), Value::Int(9000));
+        });
+        runner.unwrap().join().unwrap();
+    }
+
+    #[test]
+    fn test_call_errors() {
+        let env = Env::new();
+        let mut ft = FunctionTable::new();
+
+        // 関数ではない値の呼び出しと、引数の個数の誤りがエラーになること
+        let str = "x = 1; y = x(2)";
+        assert!(evaluator::eval(parser::parser(scanner::scanner(str).unwrap()).unwrap(), &env, &mut ft).is_err());
+        let str = "f = |x, y| x + y; y = f(2)";
+        assert!(evaluator::eval(parser::parser(scanner::scanner(str).unwrap()).unwrap(), &env, &mut ft).is_err());
+    }
+
+    #[test]
+    fn test_while() {
+        let str = "i = 0; s = 0; while i < 10000 { s = s + i; i = i + 1 }";
+        let env = Env::new();
+        let mut ft = FunctionTable::new();
+
+        // 再帰の深さに関係なく繰り返せること
+        evaluator::eval(parser::parser(scanner::scanner(str).unwrap()).unwrap(), &env, &mut ft).unwrap();
+        assert_eq!(env.get("s").unwrap(), Value::Int(49995000));
+        assert_eq!(env.get("i").unwrap(), Value::Int(10000));
+    }
+
+    #[test]
+    fn test_for() {
+        let str = "s = 0; for i in 1..5 { s = s + i }";
+        let env = Env::new();
+        let mut ft = FunctionTable::new();
+
+        // 終端を含まない範囲で繰り返すこと
+        evaluator::eval(parser::parser(scanner::scanner(str).unwrap()).unwrap(), &env, &mut ft).unwrap();
+        assert_eq!(env.get("s").unwrap(), Value::Int(10));
+    }
+
+    #[test]
+    fn test_break_continue() {
+        let str = "s = 0; for i in 0..10 { if i == 3 { continue } else { }; if i == 6 { break } else { }; s = s + i }";
+        let env = Env::new();
+        let mut ft = FunctionTable::new();
+
+        // continue で次の繰り返しへ進み、break でループを抜けること
+        evaluator::eval(parser::parser(scanner::scanner(str).unwrap()).unwrap(), &env, &mut ft).unwrap();
+        assert_eq!(env.get("s").unwrap(), Value::Int(12));
+        assert_eq!(env.get("i").unwrap(), Value::Int(6));
+    }
+
+    #[test]
+    fn test_return_in_loop() {
+        let str = "fn find(n) { i = 0; while 1 { if i * i > n { return i } else { }; i = i + 1 } }; x = find(50)";
+        let env = Env::new();
+        let mut ft = FunctionTable::new();
+
+        // ループの中の return で関数を抜けること
+        evaluator::eval(parser::parser(scanner::scanner(str).unwrap()).unwrap(), &env, &mut ft).unwrap();
+        assert_eq!(env.get("x").unwrap(), Value::Int(8));
+    }
+
+    #[test]
+    fn test_break_outside_loop() {
+        // ループの外や、ループの中で定義した関数の中の break はパースエラーになること
+        let str = "x = 1; break";
+        assert!(parser::parser(scanner::scanner(str).unwrap()).is_err());
+        let str = "while 1 { fn f(x) { continue } }";
+        assert!(parser::parser(scanner::scanner(str).unwrap()).is_err());
+    }
+
+    #[test]
+    fn test_if_without_else() {
+        let str = "x = 1; if 0 { x = 2 }; if 1 { y = 3 }";
+        let env = Env::new();
+        let mut ft = FunctionTable::new();
+
+        // else を省略できること
+        evaluator::eval(parser::parser(scanner::scanner(str).unwrap()).unwrap(), &env, &mut ft).unwrap();
+        assert_eq!(env.get("x").unwrap(), Value::Int(1));
+        assert_eq!(env.get("y").unwrap(), Value::Int(3));
+    }
+
+    #[test]
+    fn test_else_if() {
+        let str = "fn sign(n) { if n < 0 { return -1 } else if n == 0 { return 0 } else { return 1 } }; x = sign(-5); y = sign(0); z = sign(5)";
+        let env = Env::new();
+        let mut ft = FunctionTable::new();
+
+        // else if の連鎖で分岐できること
+        evaluator::eval(parser::parser(scanner::scanner(str).unwrap()).unwrap(), &env, &mut ft).unwrap();
+        assert_eq!(env.get("x").unwrap(), Value::Int(-1));
+        assert_eq!(env.get("y").unwrap(), Value::Int(0));
+        assert_eq!(env.get("z").unwrap(), Value::Int(1));
+    }
+
+    #[test]
+    fn test_if_expr() {
+        let str = "fn fib(n) { return if n < 3 { 1 } else { fib(n - 1) + fib(n - 2) } }; x = fib(10); y = if x > 100 { 1 } else if x > 50 { 2 } else { 3 }; z = if 0 { 1 }";
+        let env = Env::new();
+        let mut ft = FunctionTable::new();
+
+        // if が値を返す式として使えること
+        evaluator::eval(parser::parser(scanner::scanner(str).unwrap()).unwrap(), &env, &mut ft).unwrap();
+        assert_eq!(env.get("x").unwrap(), Value::Int(55));
+        assert_eq!(env.get("y").unwrap(), Value::Int(2));
+        assert_eq!(env.get("z").unwrap(), Value::Unit);
+    }
+
+    #[test]
+    fn test_float_literal() {
+        let str = "x = 1.5; y = 1e-3; z = 2.5E2; w = -0.25";
+        let env = Env::new();
+        let mut ft = FunctionTable::new();
+
+        // 小数と指数表記のリテラルを扱えること
+        evaluator::eval(parser::parser(scanner::scanner(str).unwrap()).unwrap(), &env, &mut ft).unwrap();
+        assert_eq!(env.get("x").unwrap(), Value::Float(1.5));
+        assert_eq!(env.get("y").unwrap(), Value::Float(0.001));
+        assert_eq!(env.get("z").unwrap(), Value::Float(250.0));
+        assert_eq!(env.get("w").unwrap(), Value::Float(-0.25));
+    }
+
+    #[test]
+    fn test_float_promotion() {
+        let str = "a = 7 / 2; b = 7.0 / 2; c = 1 + 0.5; d = 2 * 1.5; e = 3 - 0.5; f = 1 < 1.5; g = 2 == 2.0";
+        let env = Env::new();
+        let mut ft = FunctionTable::new();
+
+        // 整数と浮動小数点数を混ぜた演算は浮動小数点数になること
+        evaluator::eval(parser::parser(scanner::scanner(str).unwrap()).unwrap(), &env, &mut ft).unwrap();
+        assert_eq!(env.get("a").unwrap(), Value::Int(3));
+        assert_eq!(env.get("b").unwrap(), Value::Float(3.5));
+        assert_eq!(env.get("c").unwrap(), Value::Float(1.5));
+        assert_eq!(env.get("d").unwrap(), Value::Float(3.0));
+        assert_eq!(env.get("e").unwrap(), Value::Float(2.5));
+        assert_eq!(env.get("f").unwrap(), Value::Bool(true));
+        assert_eq!(env.get("g").unwrap(), Value::Bool(true));
+        assert_eq!(env.get("d").unwrap().to_string(), "3.0");
+    }
+
+    #[test]
+    fn test_int_float_conversion() {
+        let str = "a = int(3.9); b = int(-3.9); c = float(2); d = int(\"42\"); e = float(\"0.5\")";
+        let env = Env::new();
+        let mut ft = FunctionTable::new();
+
+        // int と float で相互に変換できること
+        evaluator::eval(parser::parser(scanner::scanner(str).unwrap()).unwrap(), &env, &mut ft).unwrap();
+        assert_eq!(env.get("a").unwrap(), Value::Int(3));
+        assert_eq!(env.get("b").unwrap(), Value::Int(-3));
+        assert_eq!(env.get("c").unwrap(), Value::Float(2.0));
+        assert_eq!(env.get("d").unwrap(), Value::Int(42));
+        assert_eq!(env.get("e").unwrap(), Value::Float(0.5));
+    }
+
+    #[test]
+    fn test_overflow_promotion() {
+        let str = "fn fib(n) { a = 0; b = 1; for i in 0..n { t = a + b; a = b; b = t }; return a }; x = fib(50); y = x - x + 1; z = 2147483647 + 1; w = -2147483648 - 1";
+        let env = Env::new();
+        let mut ft = FunctionTable::new();
+
+        // 桁あふれした整数は多倍長整数になり、範囲内に戻れば Int に戻ること
+        evaluator::eval(parser::parser(scanner::scanner(str).unwrap()).unwrap(), &env, &mut ft).unwrap();
+        assert_eq!(env.get("x").unwrap(), Value::BigInt(BigInt::from(12586269025i64)));
+        assert_eq!(env.get("x").unwrap().to_string(), "12586269025");
+        assert_eq!(env.get("y").unwrap(), Value::Int(1));
+        assert_eq!(env.get("z").unwrap(), Value::BigInt(BigInt::from(2147483648i64)));
+        assert_eq!(env.get("w").unwrap(), Value::BigInt(BigInt::from(-2147483649i64)));
+    }
+
+    #[test]
+    fn test_big_literal() {
+        let str = "x = 123456789012345678901234567890 * 10; y = x / 100000000000000000000000000000; z = -2147483648; c = x > 1; f = x * 1.0";
+        let env = Env::new();
+        let mut ft = FunctionTable::new();
+
+        // 任意の桁数の整数リテラルを扱えること
+        evaluator::eval(parser::parser(scanner::scanner(str).unwrap()).unwrap(), &env, &mut ft).unwrap();
+        assert_eq!(env.get("x").unwrap().to_string(), "1234567890123456789012345678900");
+        assert_eq!(env.get("y").unwrap(), Value::Int(12));
+        assert_eq!(env.get("z").unwrap(), Value::Int(i32::MIN));
+        assert_eq!(env.get("c").unwrap(), Value::Bool(true));
+        assert_eq!(env.get("f").unwrap(), Value::Float(1.2345678901234568e30));
+    }
+
+    #[test]
+    fn test_rational_mode() {
+        let str = "a = 7 / 2; pragma rational; x = 1 / 3 + 1 / 6; y = 1 / 3 * 3; z = 2 / 3 > 1 / 2; f = float(1 / 4); pragma rational = 0; b = 7 / 2";
+        let env = Env::new();
+        let mut ft = FunctionTable::new();
+
+        // 有理数モードでは整数どうしの除算が誤差のない分数になること
+        evaluator::eval(parser::parser(scanner::scanner(str).unwrap()).unwrap(), &env, &mut ft).unwrap();
+        assert_eq!(env.get("a").unwrap(), Value::Int(3));
+        assert_eq!(env.get("x").unwrap().to_string(), "1/2");
+        assert_eq!(env.get("y").unwrap(), Value::Int(1));
+        assert_eq!(env.get("z").unwrap(), Value::Bool(true));
+        assert_eq!(env.get("f").unwrap(), Value::Float(0.25));
+        assert_eq!(env.get("b").unwrap(), Value::Int(3));
+    }
+
+    #[test]
+    fn test_rational_settings() {
+        let str = "fn half(n) { return n / 2 }; x = half(3); y = rational(6, 4) + 1";
+        let env = Env::new();
+        env.update_settings(|settings| settings.rational = true);
+        let mut ft = FunctionTable::new();
+
+        // 設定は関数の中からも参照され、rational 関数で有理数を作れること
+        evaluator::eval(parser::parser(scanner::scanner(str).unwrap()).unwrap(), &env, &mut ft).unwrap();
+        assert_eq!(env.get("x").unwrap().to_string(), "3/2");
+        assert_eq!(env.get("y").unwrap().to_string(), "5/2");
+
+        // 存在しない設定はエラーになること
+        let env = Env::new();
+        let error = evaluator::eval(parser::parser(scanner::scanner("pragma foo").unwrap()).unwrap(), &env, &mut ft).unwrap_err();
+        assert_eq!(error.message, "設定 \"foo\" は存在しない");
+    }
+
+    #[test]
+    fn test_fixed() {
+        let str = "pragma rational; a = fixed(1 / 3, 5); b = fixed(2 / 3, 0); c = fixed(0 - 1 / 8, 2); d = fixed(1 / 200, 1); e = fixed(5, 2); f = fixed(0.125, 2)";
+        let env = Env::new();
+        let mut ft = FunctionTable::new();
+
+        // 指定した桁数の 10 進数の文字列に変換されること
+        evaluator::eval(parser::parser(scanner::scanner(str).unwrap()).unwrap(), &env, &mut ft).unwrap();
+        assert_eq!(env.get("a").unwrap(), Value::String("0.33333".to_string()));
+        assert_eq!(env.get("b").unwrap(), Value::String("1".to_string()));
+        assert_eq!(env.get("c").unwrap(), Value::String("-0.12".to_string()));
+        assert_eq!(env.get("d").unwrap(), Value::String("0.0".to_string()));
+        assert_eq!(env.get("e").unwrap(), Value::String("5.00".to_string()));
+        assert_eq!(env.get("f").unwrap(), Value::String("0.12".to_string()));
+
+        // 浮動小数点数と有理数は同じ方法で、設定 rounding に従って丸められること
+        let str = "pragma rounding = \"half_up\"; a = fixed(0.125, 2); b = fixed(rational(1, 8), 2); c = fixed(0.0 - 2.5, 0)";
+        evaluator::eval(parser::parser(scanner::scanner(str).unwrap()).unwrap(), &env, &mut ft).unwrap();
+        assert_eq!(env.get("a").unwrap(), Value::String("0.13".to_string()));
+        assert_eq!(env.get("b").unwrap(), Value::String("0.13".to_string()));
+        assert_eq!(env.get("c").unwrap(), Value::String("-3".to_string()));
+
+        // 桁数が大きすぎる場合はエラーになること
+        let error = evaluator::eval(parser::parser(scanner::scanner("s = fixed(1, 2000000000)").unwrap()).unwrap(), &env, &mut ft).unwrap_err();
+        assert_eq!(error.message, "fixed 関数の桁数には 0 以上 1000 以下の整数を指定するが 2000000000 が指定された");
+    }
+
+    #[test]
+    fn test_decimal() {
+        let str = "price = 12.50d; total = price * 3; tax = total * 0.08d; sum = 0.1d + 0.2d; eq = sum == 0.3d; n = scale(12.500d); f = float(total)";
+        let env = Env::new();
+        let mut ft = FunctionTable::new();
+
+        // 小数の演算結果は誤差なく、既定の小数点以下 2 桁で表されること
+        evaluator::eval(parser::parser(scanner::scanner(str).unwrap()).unwrap(), &env, &mut ft).unwrap();
+        assert_eq!(env.get("price").unwrap().to_string(), "12.50");
+        assert_eq!(env.get("total").unwrap().to_string(), "37.50");
+        assert_eq!(env.get("tax").unwrap().to_string(), "3.00");
+        assert_eq!(env.get("sum").unwrap().to_string(), "0.30");
+        assert_eq!(env.get("eq").unwrap(), Value::Bool(true));
+        assert_eq!(env.get("n").unwrap(), Value::Int(3));
+        assert_eq!(env.get("f").unwrap(), Value::Float(37.5));
+    }
+
+    #[test]
+    fn test_decimal_rounding() {
+        let str = "a = 0.125d + 0; pragma rounding = \"half_up\"; b = 0.125d + 0; pragma rounding = \"truncate\"; c = 0.129d + 0; pragma scale = 4; d = 1d / 3; e = round(2.5d, 0, \"half_even\"); f = round(2.5d, 0, \"half_up\"); g = round(1.2345d, 2)";
+        let env = Env::new();
+        let mut ft = FunctionTable::new();
+
+        // 設定した桁数と丸め方が演算ごとに適用されること
+        evaluator::eval(parser::parser(scanner::scanner(str).unwrap()).unwrap(), &env, &mut ft).unwrap();
+        assert_eq!(env.get("a").unwrap().to_string(), "0.12");
+        assert_eq!(env.get("b").unwrap().to_string(), "0.13");
+        assert_eq!(env.get("c").unwrap().to_string(), "0.12");
+        assert_eq!(env.get("d").unwrap().to_string(), "0.3333");
+        assert_eq!(env.get("e").unwrap().to_string(), "2");
+        assert_eq!(env.get("f").unwrap().to_string(), "3");
+        assert_eq!(env.get("g").unwrap().to_string(), "1.23");
+    }
+
+    #[test]
+    fn test_decimal_error() {
+        let env = Env::new();
+        let mut ft = FunctionTable::new();
+
+        // 小数と浮動小数点数は混ぜて計算できないこと
+        let error = evaluator::eval(parser::parser(scanner::scanner("x = 1.5d + 0.5").unwrap()).unwrap(), &env, &mut ft).unwrap_err();
+        assert_eq!(error.message, "加算処理が未定義");
+
+        // 0 での割り算はエラーになること
+        let error = evaluator::eval(parser::parser(scanner::scanner("x = 1.5d / 0").unwrap()).unwrap(), &env, &mut ft).unwrap_err();
+        assert_eq!(error.message, "1.5 / 0 の0での割り算が発生した");
+
+        // 表現できない桁数の小数リテラルはスキャンエラーになること
+        let error = scanner::scanner("x = 1.00000000000000000000000000000000001d").unwrap_err();
+        assert_eq!(error.items[0].kind, LexErrorKind::InvalidNumber("1.00000000000000000000000000000000001d".to_string()));
+    }
+
+    #[test]
+    fn test_complex() {
+        let str = "z = 3 + 4i; w = z * conj(z); q = (2 + 4i) / 2i; a = abs(z); r = re(z); i = im(z); n = -2i - 1; e = z == 3 + 4i";
+        let env = Env::new();
+        let mut ft = FunctionTable::new();
+
+        // 虚数リテラルを含む式を複素数として計算できること
+        evaluator::eval(parser::parser(scanner::scanner(str).unwrap()).unwrap(), &env, &mut ft).unwrap();
+        assert_eq!(env.get("z").unwrap().to_string(), "3+4i");
+        assert_eq!(env.get("w").unwrap().to_string(), "25+0i");
+        assert_eq!(env.get("q").unwrap().to_string(), "2-1i");
+        assert_eq!(env.get("a").unwrap(), Value::Float(5.0));
+        assert_eq!(env.get("r").unwrap(), Value::Float(3.0));
+        assert_eq!(env.get("i").unwrap(), Value::Float(4.0));
+        assert_eq!(env.get("n").unwrap().to_string(), "-1-2i");
+        assert_eq!(env.get("e").unwrap(), Value::Bool(true));
+    }
+
+    #[test]
+    fn test_complex_polar() {
+        let str = "z = polar(2, 0); t = arg(1i); u = arg(0 - 1); v = 2i";
+        let env = Env::new();
+        let mut ft = FunctionTable::new();
+
+        // 極形式から複素数を作成し、偏角を求められること
+        evaluator::eval(parser::parser(scanner::scanner(str).unwrap()).unwrap(), &env, &mut ft).unwrap();
+        assert_eq!(env.get("z").unwrap().to_string(), "2+0i");
+        assert_eq!(env.get("t").unwrap(), Value::Float(std::f64::consts::FRAC_PI_2));
+        assert_eq!(env.get("u").unwrap(), Value::Float(std::f64::consts::PI));
+        assert_eq!(env.get("v").unwrap().to_string(), "2i");
+
+        // 複素数の大小比較はエラーになること
+        let error = evaluator::eval(parser::parser(scanner::scanner("x = 1i < 2i").unwrap()).unwrap(), &env, &mut ft).unwrap_err();
+        assert_eq!(error.message, "複素数の大小比較は未定義");
+    }
+
+    #[test]
+    fn test_unit() {
+        let str = "d = 5 km; t = 20 min; v = d / t; w = to(v, \"km/h\"); l = 1 km + 500 m; a = 9.8 m/s^2 * 2 kg; r = 3 km / 500 m; c = 1 mi > 1 km";
+        let env = Env::new();
+        let mut ft = FunctionTable::new();
+
+        // 単位つきの量を計算し、別の単位に換算できること
+        evaluator::eval(parser::parser(scanner::scanner(str).unwrap()).unwrap(), &env, &mut ft).unwrap();
+        assert_eq!(env.get("v").unwrap().to_string(), "0.25 km/min");
+        assert_eq!(env.get("w").unwrap().to_string(), "15 km/h");
+        assert_eq!(env.get("l").unwrap().to_string(), "1.5 km");
+        assert_eq!(env.get("a").unwrap().to_string(), "19.6 m*kg/s^2");
+        assert_eq!(env.get("r").unwrap(), Value::Float(6.0));
+        assert_eq!(env.get("c").unwrap(), Value::Bool(true));
+    }
+
+    #[test]
+    fn test_unit_error() {
+        let env = Env::new();
+        let mut ft = FunctionTable::new();
+
+        // 次元の異なる量は加算できないこと
+        let error = evaluator::eval(parser::parser(scanner::scanner("x = 5 m + 3 s").unwrap()).unwrap(), &env, &mut ft).unwrap_err();
+        assert_eq!(error.message, "5 m と 3 s は次元が異なるため計算できない");
+        assert_eq!(error.span, Span { start: 4, end: 13, line: 1, col: 5 });
+
+        // 次元の異なる単位には換算できないこと
+        let error = evaluator::eval(parser::parser(scanner::scanner("x = to(5 m, \"kg\")").unwrap()).unwrap(), &env, &mut ft).unwrap_err();
+        assert_eq!(error.message, "5 m は kg と次元が異なるため換算できない");
+
+        // 単位表にない識別子は単位として扱わないこと
+        let tokens = scanner::scanner("x = 5 foo").unwrap();
+        assert_eq!(tokens[3].token, Token::IDENT("foo".to_string()));
+
+        // 単位の指数が i32 に収まらない計算はエラーになること
+        let error = evaluator::eval(parser::parser(scanner::scanner("x = (2 m^2) ** 2000000000").unwrap()).unwrap(), &env, &mut ft).unwrap_err();
+        assert_eq!(error.message, "2 m^2 ** 2000000000 は単位の指数が大きすぎるため計算できない");
+        let error = evaluator::eval(parser::parser(scanner::scanner("x = 5 m^2147483647 * 1 m").unwrap()).unwrap(), &env, &mut ft).unwrap_err();
+        assert_eq!(error.message, "5 m^2147483647 * 1 m は単位の指数が大きすぎるため計算できない");
+    }
+
+    #[test]
+    fn test_list() {
+        let str = "xs = [3, 1, 2]; a = xs[0]; b = xs[1..3]; c = xs[..1] + [9]; ys = xs; push(ys, 4); n = len(xs); p = pop(xs); s = sort(xs); r = reverse([1, \"a\"]); e = []";
+        let env = Env::new();
+        let mut ft = FunctionTable::new();
+
+        // リストの作成、添字、範囲、連結と組み込み関数が使えること
+        evaluator::eval(parser::parser(scanner::scanner(str).unwrap()).unwrap(), &env, &mut ft).unwrap();
+        assert_eq!(env.get("a").unwrap(), Value::Int(3));
+        assert_eq!(env.get("b").unwrap().to_string(), "[1, 2]");
+        assert_eq!(env.get("c").unwrap().to_string(), "[3, 9]");
+        assert_eq!(env.get("n").unwrap(), Value::Int(4));
+        assert_eq!(env.get("p").unwrap(), Value::Int(4));
+        assert_eq!(env.get("s").unwrap().to_string(), "[1, 2, 3]");
+        assert_eq!(env.get("xs").unwrap().to_string(), "[3, 1, 2]");
+        assert_eq!(env.get("r").unwrap().to_string(), "[\"a\", 1]");
+        assert_eq!(env.get("e").unwrap(), Value::list(Vec::new()));
+    }
+
+    #[test]
+    fn test_sort() {
+        let str = "a = sort([3, float(\"nan\"), 1, 2.5]); b = sort([\"b\", \"a\"])";
+        let env = Env::new();
+        let mut ft = FunctionTable::new();
+
+        // NaN を含むリストも並べ替えられること
+        evaluator::eval(parser::parser(scanner::scanner(str).unwrap()).unwrap(), &env, &mut ft).unwrap();
+        assert_eq!(env.get("a").unwrap().to_string(), "[1, 2.5, 3, NaN]");
+        assert_eq!(env.get("b").unwrap().to_string(), "[\"a\", \"b\"]");
+
+        // 比較できない要素が含まれる場合はエラーになること
+        let error = evaluator::eval(parser::parser(scanner::scanner("x = sort([2, \"a\", 1])").unwrap()).unwrap(), &env, &mut ft).unwrap_err();
+        assert_eq!(error.message, "2 と a の大小比較は未定義");
+    }
+
+    #[test]
+    fn test_cyclic_value() {
+        let str = "xs = [1]; push(xs, xs); ys = [1]; push(ys, ys); a = xs == xs; b = xs == ys; c = xs == [1, [1]]; m = {}; m[\"self\"] = m; struct Node { next }; n = Node { next: 0 }; n.next = n";
+        let env = Env::new();
+        let mut ft = FunctionTable::new();
+
+        // 自身を含むリストや辞書、構造体は循環している箇所を省略して表示すること
+        evaluator::eval(parser::parser(scanner::scanner(str).unwrap()).unwrap(), &env, &mut ft).unwrap();
+        assert_eq!(env.get("xs").unwrap().to_string(), "[1, [...]]");
+        assert_eq!(format!("{:?}", env.get("xs").unwrap()), "List([Int(1), List([...])])");
+        assert_eq!(env.get("m").unwrap().to_string(), "{\"self\": {...}}");
+        assert_eq!(env.get("n").unwrap().to_string(), "Node { next: Node { ... } }");
+
+        // 自身を含む値どうしも比較できること
+        assert_eq!(env.get("a").unwrap(), Value::Bool(true));
+        assert_eq!(env.get("b").unwrap(), Value::Bool(true));
+        assert_eq!(env.get("c").unwrap(), Value::Bool(false));
+    }
+
+    #[test]
+    fn test_list_index_error() {
+        let env = Env::new();
+        let mut ft = FunctionTable::new();
+
+        // 範囲外の添字はエラーになること
+        let error = evaluator::eval(parser::parser(scanner::scanner("xs = [1, 2]; x = xs[2]").unwrap()).unwrap(), &env, &mut ft).unwrap_err();
+        assert_eq!(error.message, "インデックス 2 は範囲外（長さ 2）");
+        assert_eq!(error.span, Span { start: 17, end: 22, line: 1, col: 18 });
+
+        let error = evaluator::eval(parser::parser(scanner::scanner("xs = [1, 2]; x = xs[1..3]").unwrap()).unwrap(), &env, &mut ft).unwrap_err();
+        assert_eq!(error.message, "インデックス 3 は範囲外（長さ 2）");
+
+        let error = evaluator::eval(parser::parser(scanner::scanner("x = pop([])").unwrap()).unwrap(), &env, &mut ft).unwrap_err();
+        assert_eq!(error.message, "空のリストから要素を取り出そうとした");
+    }
+
+    #[test]
+    fn test_map() {
+        let str = "fn stats(xs) { s = sort(xs); return { \"min\": s[0], max: s[len(s) - 1] } }; m = stats([3, 9, 1]); a = m.min; b = m[\"max\"]; m.sum = 13; m[\"n\"] = 3; k = keys(m); h = has(m, \"sum\"); r = remove(m, \"n\"); v = values(m); e = {}; g = { 1 + 2 }";
+        let env = Env::new();
+        let mut ft = FunctionTable::new();
+
+        // 辞書の作成、参照、代入と組み込み関数が使えること
+        evaluator::eval(parser::parser(scanner::scanner(str).unwrap()).unwrap(), &env, &mut ft).unwrap();
+        assert_eq!(env.get("a").unwrap(), Value::Int(1));
+        assert_eq!(env.get("b").unwrap(), Value::Int(9));
+        assert_eq!(env.get("k").unwrap().to_string(), "[\"max\", \"min\", \"n\", \"sum\"]");
+        assert_eq!(env.get("h").unwrap(), Value::Bool(true));
+        assert_eq!(env.get("r").unwrap(), Value::Int(3));
+        assert_eq!(env.get("v").unwrap().to_string(), "[9, 1, 13]");
+        assert_eq!(env.get("m").unwrap().to_string(), "{\"max\": 9, \"min\": 1, \"sum\": 13}");
+        assert_eq!(env.get("e").unwrap().to_string(), "{}");
+        assert_eq!(env.get("g").unwrap(), Value::Int(3));
+    }
+
+    #[test]
+    fn test_nested_assign() {
+        let str = "m = { xs: [1, 2], inner: { a: 1 } }; m.xs[1] = 5; m.inner.a = 2; ys = [0]; ys[0] = 7";
+        let env = Env::new();
+        let mut ft = FunctionTable::new();
+
+        // 入れ子になった要素へ代入できること
+        evaluator::eval(parser::parser(scanner::scanner(str).unwrap()).unwrap(), &env, &mut ft).unwrap();
+        assert_eq!(env.get("m").unwrap().to_string(), "{\"inner\": {\"a\": 2}, \"xs\": [1, 5]}");
+        assert_eq!(env.get("ys").unwrap().to_string(), "[7]");
+
+        // 存在しないキーの参照はエラーになること
+        let error = evaluator::eval(parser::parser(scanner::scanner("m = {}; x = m.foo").unwrap()).unwrap(), &env, &mut ft).unwrap_err();
+        assert_eq!(error.message, "キー \"foo\" が存在しない");
+    }
+
+    #[test]
+    fn test_struct() {
+        let str = "struct Point { x, y }; p = Point { y: 2, x: 1 }; q = p; q.x = 5; a = p.x + p.y; e1 = p == Point { x: 5, y: 2 }; e2 = p == Point { x: 1, y: 2 }; if p.x > 1 { r = 1 }";
+        let env = Env::new();
+        let mut ft = FunctionTable::new();
+
+        // 構造体を宣言して作成し、フィールドの参照と更新、比較ができること
+        evaluator::eval(parser::parser(scanner::scanner(str).unwrap()).unwrap(), &env, &mut ft).unwrap();
+        assert_eq!(env.get("p").unwrap().to_string(), "Point { x: 5, y: 2 }");
+        assert_eq!(env.get("a").unwrap(), Value::Int(7));
+        assert_eq!(env.get("e1").unwrap(), Value::Bool(true));
+        assert_eq!(env.get("e2").unwrap(), Value::Bool(false));
+        assert_eq!(env.get("r").unwrap(), Value::Int(1));
+    }
+
+    #[test]
+    fn test_struct_error() {
+        let env = Env::new();
+        let mut ft = FunctionTable::new();
+        let run = |str: &str, ft: &mut FunctionTable| evaluator::eval(parser::parser(scanner::scanner(str).unwrap()).unwrap(), &env, ft).unwrap_err().message;
+
+        // フィールドの過不足や存在しない型はエラーになること
+        assert_eq!(run("struct P { x, y }; p = P { x: 1 }", &mut ft), "構造体 P のフィールド y が指定されていない");
+        assert_eq!(run("struct P { x }; p = P { x: 1, z: 2 }", &mut ft), "構造体 P にフィールド z は存在しない");
+        assert_eq!(run("struct P { x }; p = P { x: 1 }; a = p.z", &mut ft), "構造体 P にフィールド z は存在しない");
+        assert_eq!(run("struct P { x }; p = P { x: 1 }; p.z = 1", &mut ft), "構造体 P にフィールド z は存在しない");
+        assert_eq!(run("p = Q { x: 1 }", &mut ft), "構造体 Q が宣言されていない");
+        assert_eq!(run("struct P { x, x }", &mut ft), "構造体 P のフィールド x が重複している");
+    }
+
+    #[test]
+    fn test_higher_order() {
+        let str = "fn square(x) { return x * x }; xs = range(1, 6); a = map(xs, square); b = filter(xs, |x| x > 2); c = reduce(xs, 0, |acc, x| acc + x); d = range(10, 0, 0 - 3); e = any(xs, |x| x > 4); f = all(xs, |x| x > 4); g = zip([1, 2, 3], [\"a\", \"b\"]); h = enumerate([\"x\"]); i = map([0 - 1, 2], abs)";
+        let env = Env::new();
+        let mut ft = FunctionTable::new();
+
+        // 関数を引数に取る組み込み関数が使えること
+        evaluator::eval(parser::parser(scanner::scanner(str).unwrap()).unwrap(), &env, &mut ft).unwrap();
+        assert_eq!(env.get("xs").unwrap().to_string(), "[1, 2, 3, 4, 5]");
+        assert_eq!(env.get("a").unwrap().to_string(), "[1, 4, 9, 16, 25]");
+        assert_eq!(env.get("b").unwrap().to_string(), "[3, 4, 5]");
+        assert_eq!(env.get("c").unwrap(), Value::Int(15));
+        assert_eq!(env.get("d").unwrap().to_string(), "[10, 7, 4, 1]");
+        assert_eq!(env.get("e").unwrap(), Value::Bool(true));
+        assert_eq!(env.get("f").unwrap(), Value::Bool(false));
+        assert_eq!(env.get("g").unwrap().to_string(), "[[1, \"a\"], [2, \"b\"]]");
+        assert_eq!(env.get("h").unwrap().to_string(), "[[0, \"x\"]]");
+        assert_eq!(env.get("i").unwrap().to_string(), "[1, 2]");
+    }
+
+    #[test]
+    fn test_higher_order_error() {
+        let str = "xs = map([1, 0], |x| 1 / x)";
+        let env = Env::new();
+        let mut ft = FunctionTable::new();
+
+        // 渡した関数の中で発生したエラーは、その関数の中の位置で報告されること
+        let error = evaluator::eval(parser::parser(scanner::scanner(str).unwrap()).unwrap(), &env, &mut ft).unwrap_err();
+        assert_eq!(error.message, "1 / 0 の0での割り算が発生した");
+        assert_eq!(error.span, Span { start: 21, end: 26, line: 1, col: 22 });
+
+        let error = evaluator::eval(parser::parser(scanner::scanner("xs = range(1, 5, 0)").unwrap()).unwrap(), &env, &mut ft).unwrap_err();
+        assert_eq!(error.message, "range 関数の step に 0 は指定できない");
+
+        // 作るリストが長すぎる場合はエラーになること
+        let error = evaluator::eval(parser::parser(scanner::scanner("n = len(range(2000000000))").unwrap()).unwrap(), &env, &mut ft).unwrap_err();
+        assert_eq!(error.message, "range 関数で作れるリストの長さは 1000000 までだが 2000000000 個の要素が指定された");
+
+        // any と all は結果が決まった時点で残りの要素に関数を適用しないこと
+        evaluator::eval(parser::parser(scanner::scanner("a = any([1, 0], |x| 1 / x > 0); b = all([1, 0], |x| 1 / x > 1)").unwrap()).unwrap(), &env, &mut ft).unwrap();
+        assert_eq!(env.get("a").unwrap(), Value::Bool(true));
+        assert_eq!(env.get("b").unwrap(), Value::Bool(false));
+    }
+
+    #[test]
+    fn test_math() {
+        let str = "a = max(3, 7, 5); b = min([4, 2, 9]); c = pow(2, 100); d = pow(1.005d, 2); q = sqrt(16); f = sqrt(2); g = floor(7 / 2.0); h = ceil(rational(7, 2)); i = gcd(12, 18); j = lcm(4, 6); k = factorial(20); l = binomial(10, 3); m = is_prime(1000000007); n = is_prime(561); o = cos(pi); p = ln(e)";
+        let env = Env::new();
+        let mut ft = FunctionTable::new();
+
+        // 数学関数と定数が使えること
+        evaluator::eval(parser::parser(scanner::scanner(str).unwrap()).unwrap(), &env, &mut ft).unwrap();
+        assert_eq!(env.get("a").unwrap(), Value::Int(7));
+        assert_eq!(env.get("b").unwrap(), Value::Int(2));
+        assert_eq!(env.get("c").unwrap().to_string(), "1267650600228229401496703205376");
+        assert_eq!(env.get("d").unwrap().to_string(), "1.01");
+        assert_eq!(env.get("q").unwrap(), Value::Int(4));
+        assert_eq!(env.get("f").unwrap(), Value::Float(std::f64::consts::SQRT_2));
+        assert_eq!(env.get("g").unwrap(), Value::Int(3));
+        assert_eq!(env.get("h").unwrap(), Value::Int(4));
+        assert_eq!(env.get("i").unwrap(), Value::Int(6));
+        assert_eq!(env.get("j").unwrap(), Value::Int(12));
+        assert_eq!(env.get("k").unwrap().to_string(), "2432902008176640000");
+        assert_eq!(env.get("l").unwrap(), Value::Int(120));
+        assert_eq!(env.get("m").unwrap(), Value::Bool(true));
+        assert_eq!(env.get("n").unwrap(), Value::Bool(false));
+        assert_eq!(env.get("o").unwrap(), Value::Float(-1.0));
+        assert_eq!(env.get("p").unwrap(), Value::Float(1.0));
+
+        // 同じ名前の変数は定数より優先されること
+        evaluator::eval(parser::parser(scanner::scanner("e = 3; x = e").unwrap()).unwrap(), &env, &mut ft).unwrap();
+        assert_eq!(env.get("x").unwrap(), Value::Int(3));
+    }
+
+    #[test]
+    fn test_math_error() {
+        let env = Env::new();
+        let mut ft = FunctionTable::new();
+        let run = |str: &str, ft: &mut FunctionTable| evaluator::eval(parser::parser(scanner::scanner(str).unwrap()).unwrap(), &env, ft).unwrap_err().message;
+
+        // 引数の個数や型、定義域の誤りはエラーになること
+        assert_eq!(run("x = max()", &mut ft), "max 関数には値を 1 個以上渡す");
+        assert_eq!(run("x = pow(2)", &mut ft), "pow 関数は引数を 2 個受け取るが 1 個渡された");
+        assert_eq!(run("x = sqrt(\"a\")", &mut ft), "sqrt 関数には実数を指定するが a が指定された");
+        assert_eq!(run("x = sqrt(0 - 4)", &mut ft), "負の数 -4 の平方根は実数にならない");
+        assert_eq!(run("x = ln(0)", &mut ft), "ln 関数は 0 に対して定義されていない");
+        assert_eq!(run("x = asin(2)", &mut ft), "asin 関数は 2 に対して定義されていない");
+        assert_eq!(run("x = gcd(1.5, 2)", &mut ft), "gcd 関数には整数を指定するが 1.5 が指定された");
+        assert_eq!(run("x = factorial(0 - 1)", &mut ft), "factorial 関数には 0 以上の整数を指定するが -1 が指定された");
+        assert_eq!(run("x = factorial(100000000)", &mut ft), "factorial 関数は 10000 以下の整数だけを計算できるが 100000000 が指定された");
+        assert_eq!(run("x = binomial(100000000, 50000000)", &mut ft), "binomial 関数は k と n - k の小さい方が 10000 以下の場合だけ計算できるが 50000000 が指定された");
+        assert_eq!(run("x = round(1.5, 400)", &mut ft), "round 関数の桁数には 0 以上 28 以下の整数を指定するが 400 が指定された");
+        assert_eq!(run("x = pow(0 - 1, 0.5)", &mut ft), "-1 の 0.5 乗は実数にならない");
+
+        // pow は ** と同じく負の指数や 0 の負のべき乗を扱うこと
+        assert_eq!(run("x = pow(2, -1)", &mut ft), "2 ** -1 は負の指数のため整数では計算できない");
+        assert_eq!(run("x = pow(0, -1)", &mut ft), "0 ** -1 の0での割り算が発生した");
+        evaluator::eval(parser::parser(scanner::scanner("pragma rational = true; x = pow(2, -2); y = pow(1.0d, 2000000000); pragma rational = false").unwrap()).unwrap(), &env, &mut ft).unwrap();
+        assert_eq!(env.get("x").unwrap().to_string(), "1/4");
+        assert_eq!(env.get("y").unwrap().to_string(), "1.00");
+    }
+
+    #[test]
+    fn test_string_escape() {
+        let str = r#"s = "a\tb\n\"c\"\\\u{65E5}""#;
+        let env = Env::new();
+        let mut ft = FunctionTable::new();
+
+        // エスケープシーケンスが文字に置き換わること
+        evaluator::eval(parser::parser(scanner::scanner(str).unwrap()).unwrap(), &env, &mut ft).unwrap();
+        assert_eq!(env.get("s").unwrap(), Value::String("a\tb\n\"c\"\\日".to_string()));
+
+        // 解釈できないエスケープシーケンスはスキャンエラーになること
+        let error = scanner::scanner(r#"s = "a\qb"; t = "\u{110000}""#).unwrap_err();
+        assert_eq!(error.items[0].kind, LexErrorKind::InvalidEscape("\\q".to_string()));
+        assert_eq!(error.items[0].span, Span { start: 6, end: 8, line: 1, col: 7 });
+        assert_eq!(error.items[1].kind, LexErrorKind::InvalidEscape("\\u{110000}".to_string()));
+    }
+
+    #[test]
+    fn test_print_string() {
+        let run = |str: &str| {
+            let env = Env::new();
+            let mut ft = FunctionTable::new();
+            evaluator::eval(parser::parser(scanner::scanner(str).unwrap()).unwrap(), &env, &mut ft).unwrap();
+            env.get("s").unwrap()
+        };
+
+        // 文字列は引用符をつけず、エスケープシーケンスを解釈した文字のまま出力すること
+        assert_eq!(builtin::format_print(&[run(r#"s = "a\nb""#)]).unwrap(), "a\nb");
+        assert_eq!(builtin::format_print(&[Value::String("x = {}, y = {}".to_string()), run(r#"s = "\"q\"""#), Value::Int(2)]).unwrap(), "x = \"q\", y = 2");
+        assert_eq!(builtin::format_print(&[Value::Int(1), run(r#"s = "a\tb""#)]).unwrap(), "1 a\tb");
+    }
+
+    #[test]
+    fn test_string_builtin() {
+        let str = r#"s = "  日本語テキスト  "; t = trim(s); a = len(t); b = substr(t, 1, 2); c = substr(t, 3); d = upper("abc"); e = lower("ABC"); f = split("a,b,,c", ","); g = join(split("xyz", ""), "-"); h = replace("aaa", "a", "bb"); i = contains(t, "本語"); j = starts_with(t, "日"); k = find(t, "テ"); l = find(t, "x"); m = repeat("ab", 3)"#;
+        let env = Env::new();
+        let mut ft = FunctionTable::new();
+
+        // 文字列の関数は文字単位で扱うこと
+        evaluator::eval(parser::parser(scanner::scanner(str).unwrap()).unwrap(), &env, &mut ft).unwrap();
+        assert_eq!(env.get("t").unwrap(), Value::String("日本語テキスト".to_string()));
+        assert_eq!(env.get("a").unwrap(), Value::Int(7));
+        assert_eq!(env.get("b").unwrap(), Value::String("本語".to_string()));
+        assert_eq!(env.get("c").unwrap(), Value::String("テキスト".to_string()));
+        assert_eq!(env.get("d").unwrap(), Value::String("ABC".to_string()));
+        assert_eq!(env.get("e").unwrap(), Value::String("abc".to_string()));
+        assert_eq!(env.get("f").unwrap().to_string(), r#"["a", "b", "", "c"]"#);
+        assert_eq!(env.get("g").unwrap(), Value::String("x-y-z".to_string()));
+        assert_eq!(env.get("h").unwrap(), Value::String("bbbbbb".to_string()));
+        assert_eq!(env.get("i").unwrap(), Value::Bool(true));
+        assert_eq!(env.get("j").unwrap(), Value::Bool(true));
+        assert_eq!(env.get("k").unwrap(), Value::Int(3));
+        assert_eq!(env.get("l").unwrap(), Value::Int(-1));
+        assert_eq!(env.get("m").unwrap(), Value::String("ababab".to_string()));
+
+        // 範囲外の位置や文字列以外の引数はエラーになること
+        let error = evaluator::eval(parser::parser(scanner::scanner("x = substr(\"abc\", 4)").unwrap()).unwrap(), &env, &mut ft).unwrap_err();
+        assert_eq!(error.message, "インデックス 4 は範囲外（長さ 3）");
+        let error = evaluator::eval(parser::parser(scanner::scanner("x = upper(1)").unwrap()).unwrap(), &env, &mut ft).unwrap_err();
+        assert_eq!(error.message, "upper 関数には文字列を指定するが 1 が指定された");
+    }
+
+    #[test]
+    fn test_comparison_operators() {
+        let str = r#"a = 2 <= 2; b = 3 >= 4; c = 1 != 2; d = "apple" < "banana"; e = "b" >= "abc"; f = [1, 2] == [1, 2.0]; g = {"k": 1} != {"k": 1}; h = 1 == "1"; i = [1] == 1; j = 100 cm == 1 m; k = 1 m == 1 s"#;
+        let env = Env::new();
+        let mut ft = FunctionTable::new();
+
+        // 比較演算子が使えること。型が異なる値どうしは等しくないこと
+        evaluator::eval(parser::parser(scanner::scanner(str).unwrap()).unwrap(), &env, &mut ft).unwrap();
+        assert_eq!(env.get("a").unwrap(), Value::Bool(true));
+        assert_eq!(env.get("b").unwrap(), Value::Bool(false));
+        assert_eq!(env.get("c").unwrap(), Value::Bool(true));
+        assert_eq!(env.get("d").unwrap(), Value::Bool(true));
+        assert_eq!(env.get("e").unwrap(), Value::Bool(true));
+        assert_eq!(env.get("f").unwrap(), Value::Bool(true));
+        assert_eq!(env.get("g").unwrap(), Value::Bool(false));
+        assert_eq!(env.get("h").unwrap(), Value::Bool(false));
+        assert_eq!(env.get("i").unwrap(), Value::Bool(false));
+        assert_eq!(env.get("j").unwrap(), Value::Bool(true));
+        assert_eq!(env.get("k").unwrap(), Value::Bool(false));
+
+        // 真偽値どうしは等しいかどうかを比べられること
+        evaluator::eval(parser::parser(scanner::scanner("t = 1 < 2; u = t == (3 > 2); v = t != (1 > 2)").unwrap()).unwrap(), &env, &mut ft).unwrap();
+        assert_eq!(env.get("u").unwrap(), Value::Bool(true));
+        assert_eq!(env.get("v").unwrap(), Value::Bool(true));
+
+        // 大小関係のない値どうしの大小比較はエラーになること
+        let error = evaluator::eval(parser::parser(scanner::scanner("x = \"a\" < 1").unwrap()).unwrap(), &env, &mut ft).unwrap_err();
+        assert_eq!(error.message, "a と 1 の大小比較は未定義");
+    }
+
+    #[test]
+    fn test_comparison_tokens() {
+        let tokens: Vec<Token> = scanner::scanner("a == b != c <= d >= e = f").unwrap().into_iter().map(|t| t.token).collect();
+
+        // 2 文字の比較演算子が 1 つのトークンになること
+        assert_eq!(tokens[1], Token::EQEQ);
+        assert_eq!(tokens[3], Token::NOTEQ);
+        assert_eq!(tokens[5], Token::LE);
+        assert_eq!(tokens[7], Token::GE);
+        assert_eq!(tokens[9], Token::EQ);
+
+        // = は比較には使えないこと
+        assert!(parser::parser(scanner::scanner("x = 1 = 1").unwrap()).is_err());
+    }
+
+    #[test]
+    fn test_logical() {
+        let str = "fn pos(n) { return n > 0 }; x = 5; a = x > 0 && x < 10; b = x < 0 || x == 5; c = !a; d = true && !false; e = false || true && false; f = false && 1 / 0 == 1; g = true || undefined; h = 1 && 0.0; i = !pos(1) || true";
+        let env = Env::new();
+        let mut ft = FunctionTable::new();
+
+        // 論理演算子は && が || より強く結合し、左辺で結果が決まれば右辺を評価しないこと
+        evaluator::eval(parser::parser(scanner::scanner(str).unwrap()).unwrap(), &env, &mut ft).unwrap();
+        assert_eq!(env.get("a").unwrap(), Value::Bool(true));
+        assert_eq!(env.get("b").unwrap(), Value::Bool(true));
+        assert_eq!(env.get("c").unwrap(), Value::Bool(false));
+        assert_eq!(env.get("d").unwrap(), Value::Bool(true));
+        assert_eq!(env.get("e").unwrap(), Value::Bool(false));
+        assert_eq!(env.get("f").unwrap(), Value::Bool(false));
+        assert_eq!(env.get("g").unwrap(), Value::Bool(true));
+        assert_eq!(env.get("h").unwrap(), Value::Bool(false));
+        assert_eq!(env.get("i").unwrap(), Value::Bool(true));
+    }
+
+    #[test]
+    fn test_logical_strict() {
+        let env = Env::new();
+        let mut ft = FunctionTable::new();
+
+        // strict を有効にすると、真偽値以外の値を論理演算に渡すとエラーになること
+        let str = "pragma strict; a = true && 1";
+        let error = evaluator::eval(parser::parser(scanner::scanner(str).unwrap()).unwrap(), &env, &mut ft).unwrap_err();
+        assert_eq!(error.message, "論理演算に真偽値ではない値 1 が指定された");
+        assert_eq!(error.span, Span { start: 27, end: 28, line: 1, col: 28 });
+
+        // 真偽値であれば strict でも計算できること。strict は false で無効にできること
+        let str = "b = !(1 < 2) || true; pragma strict = false; c = !0";
+        evaluator::eval(parser::parser(scanner::scanner(str).unwrap()).unwrap(), &env, &mut ft).unwrap();
+        assert_eq!(env.get("b").unwrap(), Value::Bool(true));
+        assert_eq!(env.get("c").unwrap(), Value::Bool(true));
+    }
+
+    #[test]
+    fn test_precedence() {
+        let str = "a = 10 - 3 - 2; b = 2 + 3 * 4 - 6 / 2; c = 1 + 2 == 3 && 2 * 2 != 5 || false; d = 16 / 4 / 2";
+        let env = Env::new();
+        let mut ft = FunctionTable::new();
+
+        // 演算子は優先順位の表に従って結合し、同じ優先順位では左結合になること
+        evaluator::eval(parser::parser(scanner::scanner(str).unwrap()).unwrap(), &env, &mut ft).unwrap();
+        assert_eq!(env.get("a").unwrap(), Value::Int(5));
+        assert_eq!(env.get("b").unwrap(), Value::Int(11));
+        assert_eq!(env.get("c").unwrap(), Value::Bool(true));
+        assert_eq!(env.get("d").unwrap(), Value::Int(2));
+    }
+
+    #[test]
+    fn test_chained_comparison() {
+        let str = "n = 0; fn mid() { global n; n = n + 1; return 2 }; a = 1 < mid() <= 3; b = 3 > 2 > 1; c = 1 == 1 == true; d = (1 == 1) == true; e = 5 < 3 < undefined; f = 1 < 2 < 3 < 0";
+        let env = Env::new();
+        let mut ft = FunctionTable::new();
+
+        // 連結した比較は隣り合う組をすべて満たす場合に真になり、途中の式は 1 回だけ評価されること
+        evaluator::eval(parser::parser(scanner::scanner(str).unwrap()).unwrap(), &env, &mut ft).unwrap();
+        assert_eq!(env.get("a").unwrap(), Value::Bool(true));
+        assert_eq!(env.get("n").unwrap(), Value::Int(1));
+        assert_eq!(env.get("b").unwrap(), Value::Bool(true));
+        assert_eq!(env.get("c").unwrap(), Value::Bool(false));
+        assert_eq!(env.get("d").unwrap(), Value::Bool(true));
+        assert_eq!(env.get("e").unwrap(), Value::Bool(false));
+        assert_eq!(env.get("f").unwrap(), Value::Bool(false));
+
+        // エラーは失敗した組の位置で報告されること
+        let error = evaluator::eval(parser::parser(scanner::scanner("x = 1 < 2 < \"a\"").unwrap()).unwrap(), &env, &mut ft).unwrap_err();
+        assert_eq!(error.message, "2 と a の大小比較は未定義");
+        assert_eq!(error.span, Span { start: 8, end: 15, line: 1, col: 9 });
+    }
+
+    #[test]
+    fn test_pow_mod_floor_div() {
+        let str = "x = 3; a = 7 % 3; b = -7 % 3; c = 7 % -3; d = 7 // 2; e = -7 // 2; f = -7.5 // 2; g = -7.5 % 2; h = 2 ** 3 ** 2; i = -2 ** 2; j = (-2) ** 2; k = 2 ** -1.0; l = -x ** 2; m = 2 ** 100; n = 1.5d ** 2; o = (3 m) ** 2; p = 2 * 3 ** 2 % 5; q = 7.5 m % 2 m";
+        let env = Env::new();
+        let mut ft = FunctionTable::new();
+
+        // 剰余は除数と同じ符号になり、切り捨て除算は負の無限大方向へ切り捨てること
+        // '**' は右結合で単項マイナスより強く結合すること
+        evaluator::eval(parser::parser(scanner::scanner(str).unwrap()).unwrap(), &env, &mut ft).unwrap();
+        assert_eq!(env.get("a").unwrap(), Value::Int(1));
+        assert_eq!(env.get("b").unwrap(), Value::Int(2));
+        assert_eq!(env.get("c").unwrap(), Value::Int(-2));
+        assert_eq!(env.get("d").unwrap(), Value::Int(3));
+        assert_eq!(env.get("e").unwrap(), Value::Int(-4));
+        assert_eq!(env.get("f").unwrap(), Value::Float(-4.0));
+        assert_eq!(env.get("g").unwrap(), Value::Float(0.5));
+        assert_eq!(env.get("h").unwrap(), Value::Int(512));
+        assert_eq!(env.get("i").unwrap(), Value::Int(-4));
+        assert_eq!(env.get("j").unwrap(), Value::Int(4));
+        assert_eq!(env.get("k").unwrap(), Value::Float(0.5));
+        assert_eq!(env.get("l").unwrap(), Value::Int(-9));
+        assert_eq!(env.get("m").unwrap().to_string(), "1267650600228229401496703205376");
+        assert_eq!(env.get("n").unwrap().to_string(), "2.25");
+        assert_eq!(env.get("o").unwrap().to_string(), "9 m^2");
+        assert_eq!(env.get("p").unwrap(), Value::Int(3));
+        assert_eq!(env.get("q").unwrap().to_string(), "1.5 m");
+    }
+
+    #[test]
+    fn test_pow_mod_floor_div_error() {
+        let env = Env::new();
+        let mut ft = FunctionTable::new();
+        let run = |str: &str, ft: &mut FunctionTable| evaluator::eval(parser::parser(scanner::scanner(str).unwrap()).unwrap(), &env, ft).unwrap_err().message;
+
+        // 0 による剰余や切り捨て除算、整数の負の指数はエラーになること
+        assert_eq!(run("x = 5 % 0", &mut ft), "5 % 0 の0での割り算が発生した");
+        assert_eq!(run("x = 5 // 0.0", &mut ft), "5 // 0.0 の0での割り算が発生した");
+        assert_eq!(run("x = 0 ** -1", &mut ft), "0 ** -1 の0での割り算が発生した");
+        assert_eq!(run("x = 2 ** -2", &mut ft), "2 ** -2 は負の指数のため整数では計算できない");
+        assert_eq!(run("x = (1 m) ** 0.5", &mut ft), "1 m ** 0.5 の指数には無次元の整数を指定する");
+        assert_eq!(run("x = 7 ** 300000000", &mut ft), "7 ** 300000000 は結果が大きすぎるため計算できない");
+        assert_eq!(run("x = -\"a\"", &mut ft), "a の符号は反転できない");
+
+        // 有理数モードでは整数の負の指数は有理数になること
+        evaluator::eval(parser::parser(scanner::scanner("pragma rational; y = 2 ** -2").unwrap()).unwrap(), &env, &mut ft).unwrap();
+        assert_eq!(env.get("y").unwrap().to_string(), "1/4");
+    }
+
+    #[test]
+    fn test_radix_literal() {
+        let tokens: Vec<Token> = scanner::scanner("0xFF 0b1010 0o17 1_000_000 0x1_0000_0000 1_000.5").unwrap().into_iter().map(|t| t.token).collect();
+
+        // 基数の接頭辞や桁区切りの '_' がついた数値リテラルを読めること
+        assert_eq!(tokens[0], Token::NUMBER(255));
+        assert_eq!(tokens[1], Token::NUMBER(10));
+        assert_eq!(tokens[2], Token::NUMBER(15));
+        assert_eq!(tokens[3], Token::NUMBER(1000000));
+        assert_eq!(tokens[4], Token::BIGNUMBER(BigInt::from(4294967296u64)));
+        assert_eq!(tokens[5], Token::FLOAT(1000.5));
+
+        // 基数に合わない数字はスキャンエラーになること
+        let error = scanner::scanner("x = 0b102").unwrap_err();
+        assert_eq!(error.items[0].kind, LexErrorKind::InvalidNumber("0b102".to_string()));
+        assert_eq!(error.items[0].span, Span { start: 4, end: 9, line: 1, col: 5 });
+    }
+
+    #[test]
+    fn test_bitwise() {
+        let str = "a = 0b1100 & 0b1010; b = 0b1100 | 0b1010; c = 0b1100 ^ 0b1010; d = ~0; e = 1 << 40; f = -16 >> 2; g = 1 + 2 << 3; h = 6 & 3 == 2; i = hex(255); j = bin(10); k = oct(-15); l = map([1, 2], |x| x | 4)";
+        let env = Env::new();
+        let mut ft = FunctionTable::new();
+
+        // ビット演算子は整数どうしで計算でき、比較より強く加減算より弱く結合すること
+        evaluator::eval(parser::parser(scanner::scanner(str).unwrap()).unwrap(), &env, &mut ft).unwrap();
+        assert_eq!(env.get("a").unwrap(), Value::Int(8));
+        assert_eq!(env.get("b").unwrap(), Value::Int(14));
+        assert_eq!(env.get("c").unwrap(), Value::Int(6));
+        assert_eq!(env.get("d").unwrap(), Value::Int(-1));
+        assert_eq!(env.get("e").unwrap().to_string(), "1099511627776");
+        assert_eq!(env.get("f").unwrap(), Value::Int(-4));
+        assert_eq!(env.get("g").unwrap(), Value::Int(24));
+        assert_eq!(env.get("h").unwrap(), Value::Bool(true));
+        assert_eq!(env.get("i").unwrap(), Value::String("0xff".to_string()));
+        assert_eq!(env.get("j").unwrap(), Value::String("0b1010".to_string()));
+        assert_eq!(env.get("k").unwrap(), Value::String("-0o17".to_string()));
+        assert_eq!(env.get("l").unwrap().to_string(), "[5, 6]");
+
+        // 整数以外のビット演算や負のシフト量はエラーになること
+        let error = evaluator::eval(parser::parser(scanner::scanner("x = 1.5 & 1").unwrap()).unwrap(), &env, &mut ft).unwrap_err();
+        assert_eq!(error.message, "1.5 と 1 の & 演算は整数どうしでだけ計算できる");
+        let error = evaluator::eval(parser::parser(scanner::scanner("x = 1 << -1").unwrap()).unwrap(), &env, &mut ft).unwrap_err();
+        assert_eq!(error.message, "シフト量には 0 以上の整数を指定するが -1 が指定された");
+        let error = evaluator::eval(parser::parser(scanner::scanner("x = 1 << 100000000000000").unwrap()).unwrap(), &env, &mut ft).unwrap_err();
+        assert_eq!(error.message, "シフト量には 65536 以下の整数を指定するが 100000000000000 が指定された");
+    }
+
+    #[test]
+    fn test_wrap() {
+        let str = "pragma wrap = \"u8\"; a = 250 + 10; b = 0 - 1; c = ~0; d = 1 << 8; e = 16 * 16 + 0.5; pragma wrap = \"i64\"; f = 0x7FFF_FFFF_FFFF_FFFF + 1; g = -(0 - 0x8000_0000_0000_0000); pragma wrap = \"u16\"; h = hex(~0x00FF); pragma wrap = \"none\"; i = 250 + 10";
+        let env = Env::new();
+        let mut ft = FunctionTable::new();
+
+        // 固定幅の設定では整数の演算結果が 2 の補数で折り返されること
+        evaluator::eval(parser::parser(scanner::scanner(str).unwrap()).unwrap(), &env, &mut ft).unwrap();
+        assert_eq!(env.get("a").unwrap(), Value::Int(4));
+        assert_eq!(env.get("b").unwrap(), Value::Int(255));
+        assert_eq!(env.get("c").unwrap(), Value::Int(255));
+        assert_eq!(env.get("d").unwrap(), Value::Int(1));
+        assert_eq!(env.get("e").unwrap(), Value::Float(0.5));
+        assert_eq!(env.get("f").unwrap().to_string(), "-9223372036854775808");
+        assert_eq!(env.get("g").unwrap().to_string(), "-9223372036854775808");
+        assert_eq!(env.get("h").unwrap(), Value::String("0xff00".to_string()));
+        assert_eq!(env.get("i").unwrap(), Value::Int(260));
+
+        // 固定幅の設定ではシフト量を幅のビット数で割った余りにすること
+        evaluator::eval(parser::parser(scanner::scanner("pragma wrap = \"u8\"; a = 1 << 9; b = 200 >> 10; pragma wrap = \"i64\"; c = 1 << 100000000000005; pragma wrap = \"none\"").unwrap()).unwrap(), &env, &mut ft).unwrap();
+        assert_eq!(env.get("a").unwrap(), Value::Int(2));
+        assert_eq!(env.get("b").unwrap(), Value::Int(50));
+        assert_eq!(env.get("c").unwrap(), Value::Int(32));
+
+        // 固定幅の設定ではべき乗も大きな指数のまま折り返して計算できること
+        evaluator::eval(parser::parser(scanner::scanner("pragma wrap = \"u8\"; a = 7 ** 300000000; b = (0 - 3) ** 3; pragma wrap = \"none\"").unwrap()).unwrap(), &env, &mut ft).unwrap();
+        assert_eq!(env.get("a").unwrap(), Value::Int(1));
+        assert_eq!(env.get("b").unwrap(), Value::Int(229));
+
+        // 整数のリテラルや符号を反転したリテラルも同じく折り返されること
+        evaluator::eval(parser::parser(scanner::scanner("pragma wrap = \"u8\"; a = -1; b = 300; x = 1; c = -x; d = 300 m; pragma wrap = \"i64\"; e = 0xFFFF_FFFF_FFFF_FFFF; pragma wrap = \"none\"").unwrap()).unwrap(), &env, &mut ft).unwrap();
+        assert_eq!(env.get("a").unwrap(), Value::Int(255));
+        assert_eq!(env.get("b").unwrap(), Value::Int(44));
+        assert_eq!(env.get("c").unwrap(), Value::Int(255));
+        assert_eq!(env.get("d").unwrap().to_string(), "300 m");
+        assert_eq!(env.get("e").unwrap(), Value::Int(-1));
+
+        // 存在しない幅はエラーになること
+        let error = evaluator::eval(parser::parser(scanner::scanner("pragma wrap = \"u7\"").unwrap()).unwrap(), &env, &mut ft).unwrap_err();
+        assert_eq!(error.message, "整数の幅 \"u7\" は存在しない");
     }
 }
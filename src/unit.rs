@@ -0,0 +1,259 @@
+use std::fmt;
+
+///
+/// 次元ベクトル
+///
+/// 長さ (m)、質量 (kg)、時間 (s)、電流 (A)、温度 (K)、物質量 (mol)、光度 (cd) の指数
+///
+pub type Dims = [i32; 7];
+
+const NONE: Dims = [0, 0, 0, 0, 0, 0, 0];
+const LENGTH: Dims = [1, 0, 0, 0, 0, 0, 0];
+const MASS: Dims = [0, 1, 0, 0, 0, 0, 0];
+const TIME: Dims = [0, 0, 1, 0, 0, 0, 0];
+const CURRENT: Dims = [0, 0, 0, 1, 0, 0, 0];
+const TEMPERATURE: Dims = [0, 0, 0, 0, 1, 0, 0];
+const AMOUNT: Dims = [0, 0, 0, 0, 0, 1, 0];
+const LUMINOUS: Dims = [0, 0, 0, 0, 0, 0, 1];
+const AREA: Dims = [2, 0, 0, 0, 0, 0, 0];
+const VOLUME: Dims = [3, 0, 0, 0, 0, 0, 0];
+const VELOCITY: Dims = [1, 0, -1, 0, 0, 0, 0];
+const FREQUENCY: Dims = [0, 0, -1, 0, 0, 0, 0];
+const FORCE: Dims = [1, 1, -2, 0, 0, 0, 0];
+const PRESSURE: Dims = [-1, 1, -2, 0, 0, 0, 0];
+const ENERGY: Dims = [2, 1, -2, 0, 0, 0, 0];
+const POWER: Dims = [2, 1, -3, 0, 0, 0, 0];
+const CHARGE: Dims = [0, 0, 1, 1, 0, 0, 0];
+const VOLTAGE: Dims = [2, 1, -3, -1, 0, 0, 0];
+const RESISTANCE: Dims = [2, 1, -3, -2, 0, 0, 0];
+
+///
+/// 単位表
+///
+/// 単位名、SI 基本単位に換算するための倍率、次元の組
+///
+/// 温度は倍率だけで換算できる K のみを扱う
+/// 数値リテラルの接尾辞や予約語と紛らわしい名前（d, i, in など）は登録しない
+///
+static UNIT_TABLE: [(&str, f64, Dims); 42] = [
+    // 長さ
+    ("m", 1.0, LENGTH),
+    ("km", 1000.0, LENGTH),
+    ("cm", 0.01, LENGTH),
+    ("mm", 0.001, LENGTH),
+    ("inch", 0.0254, LENGTH),
+    ("ft", 0.3048, LENGTH),
+    ("yd", 0.9144, LENGTH),
+    ("mi", 1609.344, LENGTH),
+    // 質量
+    ("kg", 1.0, MASS),
+    ("g", 0.001, MASS),
+    ("mg", 0.000001, MASS),
+    ("lb", 0.45359237, MASS),
+    ("oz", 0.028349523125, MASS),
+    // 時間
+    ("s", 1.0, TIME),
+    ("ms", 0.001, TIME),
+    ("min", 60.0, TIME),
+    ("h", 3600.0, TIME),
+    ("day", 86400.0, TIME),
+    // その他の基本単位
+    ("A", 1.0, CURRENT),
+    ("K", 1.0, TEMPERATURE),
+    ("mol", 1.0, AMOUNT),
+    ("cd", 1.0, LUMINOUS),
+    // 面積と体積
+    ("ha", 10000.0, AREA),
+    ("acre", 4046.8564224, AREA),
+    ("L", 0.001, VOLUME),
+    ("mL", 0.000001, VOLUME),
+    ("gal", 0.003785411784, VOLUME),
+    // 組立単位
+    ("mph", 0.44704, VELOCITY),
+    ("Hz", 1.0, FREQUENCY),
+    ("N", 1.0, FORCE),
+    ("lbf", 4.4482216152605, FORCE),
+    ("Pa", 1.0, PRESSURE),
+    ("psi", 6894.757293168, PRESSURE),
+    ("J", 1.0, ENERGY),
+    ("kJ", 1000.0, ENERGY),
+    ("cal", 4.184, ENERGY),
+    ("kWh", 3600000.0, ENERGY),
+    ("W", 1.0, POWER),
+    ("kW", 1000.0, POWER),
+    ("C", 1.0, CHARGE),
+    ("V", 1.0, VOLTAGE),
+    ("ohm", 1.0, RESISTANCE),
+];
+
+fn lookup(name: &str) -> Option<(&'static str, f64, Dims)> { UNIT_TABLE.iter().find(|(n, _, _)| *n == name).copied() }
+
+///
+/// 単位
+///
+/// * terms - 単位名と指数の組。`km/h` は [("km", 1), ("h", -1)] になる
+/// * factor - SI 基本単位に換算するための倍率
+/// * dims - 次元
+///
+#[derive(Debug, PartialEq, Clone)]
+pub struct Unit {
+    pub terms: Vec<(String, i32)>,
+    pub factor: f64,
+    pub dims: Dims,
+}
+
+impl Unit {
+    /// 無次元の単位を作成する
+    pub fn one() -> Unit {
+        Unit {
+            terms: Vec::new(),
+            factor: 1.0,
+            dims: NONE,
+        }
+    }
+
+    /// 無次元かどうか
+    pub fn is_dimensionless(&self) -> bool { self.dims == NONE }
+
+    /// self を power 乗した単位を返却する。指数が i32 に収まらない場合は None を返す
+    pub fn pow(&self, power: i32) -> Option<Unit> {
+        let mut terms = Vec::new();
+        for (name, p) in &self.terms {
+            terms.push((name.clone(), p.checked_mul(power)?));
+        }
+        terms.retain(|(_, p)| *p != 0);
+
+        let mut dims = self.dims;
+        for d in dims.iter_mut() {
+            *d = d.checked_mul(power)?;
+        }
+        Some(Unit {
+            terms,
+            factor: self.factor.powi(power),
+            dims,
+        })
+    }
+
+    /// 2 つの単位の積を返却する。同じ名前の単位は指数をまとめる。指数が i32 に収まらない場合は None を返す
+    pub fn mul(&self, other: &Unit) -> Option<Unit> {
+        let mut terms = self.terms.clone();
+        for (name, power) in &other.terms {
+            match terms.iter_mut().find(|(n, _)| n == name) {
+                Some((_, p)) => *p = p.checked_add(*power)?,
+                None => terms.push((name.clone(), *power)),
+            }
+        }
+        terms.retain(|(_, p)| *p != 0);
+
+        let mut dims = self.dims;
+        for (d, o) in dims.iter_mut().zip(other.dims) {
+            *d = d.checked_add(o)?;
+        }
+        Some(Unit {
+            terms,
+            factor: self.factor * other.factor,
+            dims,
+        })
+    }
+
+    /// 2 つの単位の商を返却する。指数が i32 に収まらない場合は None を返す
+    pub fn div(&self, other: &Unit) -> Option<Unit> { self.mul(&other.pow(-1)?) }
+}
+
+impl fmt::Display for Unit {
+    // kg*m/s^2 のように、正の指数の単位を先に並べて負の指数の単位で割る形で表示する
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let term = |name: &str, power: u32| if power == 1 { name.to_string() } else { format!("{}^{}", name, power) };
+        let numerator: Vec<String> = self.terms.iter().filter(|(_, p)| *p > 0).map(|(n, p)| term(n, p.unsigned_abs())).collect();
+        let denominator: Vec<String> = self.terms.iter().filter(|(_, p)| *p < 0).map(|(n, p)| term(n, p.unsigned_abs())).collect();
+
+        if numerator.is_empty() {
+            write!(f, "1")?;
+        } else {
+            write!(f, "{}", numerator.join("*"))?;
+        }
+        for d in denominator {
+            write!(f, "/{}", d)?;
+        }
+        Ok(())
+    }
+}
+
+///
+/// 単位つきの量
+///
+/// value は unit で表した大きさ
+///
+#[derive(Debug, PartialEq, Clone)]
+pub struct Quantity {
+    pub value: f64,
+    pub unit: Unit,
+}
+
+impl Quantity {
+    /// SI 基本単位で表した大きさを返却する
+    pub fn si(&self) -> f64 { self.value * self.unit.factor }
+
+    /// 次元の等しい単位 unit で表した量に換算する
+    pub fn convert(&self, unit: &Unit) -> Option<Quantity> {
+        if self.unit.dims != unit.dims {
+            return None;
+        }
+        Some(Quantity {
+            value: self.si() / unit.factor,
+            unit: unit.clone(),
+        })
+    }
+}
+
+impl fmt::Display for Quantity {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result { write!(f, "{} {}", self.value, self.unit) }
+}
+
+///
+/// 単位を表す文字列を解析する
+///
+/// Unit = Term { ( '*' | '/' ) Term }
+/// Term = NAME [ '^' [ '-' ] DIGITS ]
+///
+/// 空白は含められない。単位表にない名前があれば None を返す
+///
+pub fn parse(s: &str) -> Option<Unit> {
+    let mut unit = Unit::one();
+    let mut chars = s.chars().peekable();
+    let mut divide = false;
+    loop {
+        let mut name = String::new();
+        while let Some(c) = chars.next_if(|c| c.is_ascii_alphabetic()) {
+            name.push(c);
+        }
+        let (_, factor, dims) = lookup(&name)?;
+
+        let mut power: i32 = 1;
+        if chars.next_if_eq(&'^').is_some() {
+            let mut digits = String::new();
+            if chars.next_if_eq(&'-').is_some() {
+                digits.push('-');
+            }
+            while let Some(c) = chars.next_if(|c| c.is_ascii_digit()) {
+                digits.push(c);
+            }
+            power = digits.parse().ok()?;
+        }
+
+        let term = Unit {
+            terms: vec![(name, 1)],
+            factor,
+            dims,
+        }
+        .pow(if divide { power.checked_neg()? } else { power })?;
+        unit = unit.mul(&term)?;
+
+        match chars.next() {
+            Some('*') => divide = false,
+            Some('/') => divide = true,
+            Some(_) => return None,
+            None => return Some(unit),
+        }
+    }
+}